@@ -0,0 +1,292 @@
+use std::f64::consts::PI;
+
+use anyhow::Error;
+use itertools::Itertools;
+use statrs::distribution::{ChiSquared, ContinuousCDF};
+
+use crate::analysis::{check_points, TestResult};
+
+/// Ranges above this are close enough to continuous to use the limiting distribution of the
+/// continuous test.
+const DISCRETE_LIMIT: u64 = 1000;
+
+/// Number of terms kept exactly when working out the distribution of the discrete statistic.
+const KEPT_WEIGHTS: u64 = 50;
+
+/// Anderson-Darling test of `points` against the discrete uniform distribution over `0..range`.
+///
+/// This is Choulakian, Lockhart and Stephens' discrete form of the statistic, which sums over the
+/// values in the range rather than integrating, so ties don't count against the sample. It weights
+/// the tails much more heavily than the Kolmogorov-Smirnov test.
+pub fn anderson_darling(points: &[u64], range: u64) -> Result<TestResult, Error> {
+    check_points(points, range)?;
+
+    let n = points.len();
+    let statistic = discrete_statistic(points, range);
+
+    let p_value = if range > DISCRETE_LIMIT {
+        1. - anderson_darling_cdf(n, statistic)
+    } else {
+        // For large samples the statistic is distributed as the sum of X_j / (j * (j + 1)) for `j`
+        // in `1..range`, with each X_j independently chi-squared with one degree of freedom. Only
+        // the largest weights are kept, with the rest of the terms replaced by their mean, which
+        // they hardly vary from.
+        let kept = range.min(KEPT_WEIGHTS + 1);
+        let weights = (1..kept)
+            .map(|j| 1. / (j * (j + 1)) as f64)
+            .collect::<Vec<_>>();
+        let rest = 1. / kept as f64 - 1. / range as f64;
+
+        weighted_chi_squared_sf(&weights, statistic - rest)
+    };
+
+    Ok(TestResult {
+        name: "anderson-darling",
+        statistic,
+        degrees_of_freedom: None,
+        p_value,
+        two_sided: true,
+    })
+}
+
+/// The discrete Anderson-Darling statistic with equal probabilities for each value in `0..range`:
+///
+/// `n * sum((c_j - t_j)^2 / (range * t_j * (1 - t_j)))` for `j` in `1..range`, where `c_j` is the
+/// fraction of points below `j` and `t_j = j / range` the fraction expected. The terms are split
+/// into `c_j^2 / j + (1 - c_j)^2 / (range - j) - 1 / range`, so each run of values with no points
+/// adds up in one go, however large the range.
+fn discrete_statistic(points: &[u64], range: u64) -> f64 {
+    let n = points.len() as f64;
+
+    // Sum of the terms for `j` in `first..=last`, where every one has `c_j = fraction`
+    let terms = |first: u64, last: u64, fraction: f64| {
+        if first > last {
+            return 0.;
+        }
+
+        fraction * fraction * harmonic_difference(first - 1, last)
+            + (1. - fraction)
+                * (1. - fraction)
+                * harmonic_difference(range - last - 1, range - first)
+    };
+
+    let mut sum = 0.;
+    let mut below = 0;
+    let mut next = 1;
+
+    for (val, count) in points
+        .iter()
+        .sorted()
+        .dedup_with_count()
+        .map(|(c, v)| (*v, c))
+    {
+        sum += terms(next, val, below as f64 / n);
+        below += count;
+        next = val + 1;
+    }
+
+    sum += terms(next, range - 1, 1.);
+
+    (n * (sum - (range - 1) as f64 / range as f64)).max(0.)
+}
+
+/// `1 / (from + 1) + ... + 1 / to`, or 0 if `to` isn't larger than `from`.
+fn harmonic_difference(from: u64, to: u64) -> f64 {
+    if to <= from {
+        return 0.;
+    }
+
+    // Add up short runs and small terms directly, where the expansion below isn't accurate enough
+    if to - from <= 64 {
+        return (from + 1..=to).map(|j| 1. / j as f64).sum();
+    }
+
+    if from < 1000 {
+        let small = (from + 1..=to.min(1000))
+            .map(|j| 1. / j as f64)
+            .sum::<f64>();
+        return small + harmonic_difference(1000, to);
+    }
+
+    // H_m = ln(m) + γ + 1 / 2m - 1 / 12m^2 + O(1 / m^4), written to avoid cancellation
+    let (from, to) = (from as f64, to as f64);
+    let reciprocal_difference = (to - from) / (from * to);
+
+    ((to - from) / from).ln_1p() - reciprocal_difference / 2.
+        + reciprocal_difference * (1. / from + 1. / to) / 12.
+}
+
+/// Probability that `sum(w_i * X_i)` exceeds `x`, for positive `weights` `w` and each `X_i`
+/// independently chi-squared with one degree of freedom, by Imhof's method (1961).
+fn weighted_chi_squared_sf(weights: &[f64], x: f64) -> f64 {
+    if x <= 0. {
+        return 1.;
+    }
+
+    if let [weight] = weights {
+        return ChiSquared::new(1.).map_or(0., |distribution| distribution.sf(x / weight));
+    }
+
+    // P(Q > x) = 1/2 + 1/π ∫ sin(θ(u)) / (u ρ(u)) du over u from 0 to infinity
+    let theta = |u: f64| {
+        weights
+            .iter()
+            .map(|weight| (weight * u).atan() / 2.)
+            .sum::<f64>()
+            - x * u / 2.
+    };
+    let envelope = |u: f64| {
+        let rho = weights
+            .iter()
+            .map(|weight| (weight * weight * u * u).ln_1p() / 4.)
+            .sum::<f64>()
+            .exp();
+        1. / (u * rho)
+    };
+    let integrand = |u: f64| {
+        if u == 0. {
+            (weights.iter().sum::<f64>() - x) / 2.
+        } else {
+            theta(u).sin() * envelope(u)
+        }
+    };
+
+    // Small enough steps that θ turns by at most a tenth of a radian across each of them
+    let step = 0.2 / (weights.iter().sum::<f64>() + x);
+    let tolerance = 1e-7;
+
+    let mut integral = 0.;
+    let mut u = 0.;
+    let mut left = integrand(0.);
+
+    for _ in 0..1_000_000 {
+        let middle = integrand(u + step);
+        let right = integrand(u + 2. * step);
+        integral += step / 3. * (left + 4. * middle + right);
+
+        u += 2. * step;
+        left = right;
+
+        // Stop once the rest can't add up to much, either because the envelope shrinks fast
+        // enough on its own or because it's oscillating quickly enough to cancel itself out
+        let remaining = envelope(u);
+
+        if remaining * u < tolerance || (x * u > 20. && remaining < tolerance * x) {
+            break;
+        }
+    }
+
+    (0.5 + integral / PI).clamp(0., 1.)
+}
+
+/// CDF of the Anderson-Darling statistic for `n` samples, from Marsaglia and Marsaglia's
+/// "Evaluating the Anderson-Darling Distribution" (2004).
+fn anderson_darling_cdf(n: usize, z: f64) -> f64 {
+    if z <= 0. {
+        return 0.;
+    }
+
+    // Limiting distribution as n goes to infinity
+    let x = if z < 2. {
+        (-1.2337141 / z).exp() / z.sqrt()
+            * (2.00012
+                + (0.247105 - (0.0649821 - (0.0347962 - (0.011672 - 0.00168691 * z) * z) * z) * z)
+                    * z)
+    } else {
+        (-(1.0776
+            - (2.30695 - (0.43424 - (0.082433 - (0.008056 - 0.0003146 * z) * z) * z) * z) * z)
+            .exp())
+        .exp()
+    };
+
+    // Correction for finite n
+    let n = n as f64;
+    let error = if x > 0.8 {
+        (-130.2137
+            + (745.2337 - (1705.091 - (1950.646 - (1116.360 - 255.7844 * x) * x) * x) * x) * x)
+            / n
+    } else {
+        let c = 0.01265 + 0.1757 / n;
+
+        if x < c {
+            let t = x / c;
+            let t = t.sqrt() * (1. - t) * (49. * t - 102.);
+            t * (0.0037 / (n * n) + 0.00078 / n + 0.00006) / n
+        } else {
+            let t = (x - c) / (0.8 - c);
+            let t = -0.00022633
+                + (6.54034 - (14.6538 - (14.458 - (8.259 - 1.91864 * t) * t) * t) * t) * t;
+            t * (0.04213 / n + 0.01365 / (n * n)) / n
+        }
+    };
+
+    (x + error).clamp(0., 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discrete_statistic_matches_direct_sum() {
+        // One term, with a quarter of the points too many below 1
+        assert!((discrete_statistic(&[0, 0, 0, 1], 2) - 0.5).abs() < 1e-12);
+        assert!(discrete_statistic(&[0, 1, 1, 0], 2).abs() < 1e-12);
+
+        let points = (0..500u64).map(|i| i * i % 1999).collect::<Vec<_>>();
+        let n = points.len() as f64;
+        let direct = (1..1999u64)
+            .map(|j| {
+                let below = points.iter().filter(|val| **val < j).count() as f64 / n;
+                let t = j as f64 / 1999.;
+                (below - t) * (below - t) / (1999. * t * (1. - t))
+            })
+            .sum::<f64>()
+            * n;
+
+        assert!((discrete_statistic(&points, 1999) - direct).abs() < 1e-9);
+    }
+
+    #[test]
+    fn harmonic_difference_matches_direct_sum() {
+        for (from, to) in [(0, 10), (5, 5000), (1000, 200_000), (123_456, 1_234_567)] {
+            let direct = (from + 1..=to).map(|j| 1. / j as f64).sum::<f64>();
+            assert!((harmonic_difference(from, to) - direct).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn one_weight_is_scaled_chi_squared() {
+        // 3.841 is the 95th percentile of chi-squared with one degree of freedom
+        assert!((weighted_chi_squared_sf(&[0.5], 3.841 / 2.) - 0.05).abs() < 1e-4);
+    }
+
+    #[test]
+    fn imhof_matches_chi_squared_with_equal_weights() {
+        // 9.488 is the 95th percentile of chi-squared with four degrees of freedom
+        let p = weighted_chi_squared_sf(&[1.; 4], 9.488);
+        assert!((p - 0.05).abs() < 1e-4, "{}", p);
+    }
+
+    #[test]
+    fn many_weights_approach_continuous_distribution() {
+        let weights = (1..=50)
+            .map(|j| 1. / (j * (j + 1)) as f64)
+            .collect::<Vec<_>>();
+        let rest = 1. / 51.;
+
+        // 2.492 and 3.857 are the 95th and 99th percentiles of the continuous statistic
+        for (x, p) in [(2.492, 0.05), (3.857, 0.01)] {
+            assert!((weighted_chi_squared_sf(&weights, x - rest) - p).abs() < 5e-4);
+        }
+    }
+
+    #[test]
+    fn marsaglia_cdf_matches_known_percentiles() {
+        for (z, p) in [(2.492, 0.95), (3.857, 0.99)] {
+            assert!((anderson_darling_cdf(1_000_000, z) - p).abs() < 5e-4);
+        }
+
+        assert_eq!(anderson_darling_cdf(10, 0.), 0.);
+    }
+}
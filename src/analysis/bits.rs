@@ -0,0 +1,196 @@
+use std::ops::Range;
+
+use anyhow::{anyhow, Error};
+use rand::RngCore;
+use statrs::distribution::{ChiSquared, ContinuousCDF};
+
+use crate::analysis::TestResult;
+
+/// Counts of how often each bit of a generator's raw 64 bit output is set, and how often it changes
+/// from one output to the next. Bit 0 is the least significant.
+///
+/// Only the top `bits` bits are counted, as generators producing fewer than 64 bits at a time leave
+/// the rest as zero padding.
+#[derive(Clone, Debug)]
+pub struct BitStats {
+    /// Number of outputs counted.
+    pub words: u64,
+
+    /// Number of bits counted, from the most significant down.
+    pub bits: u32,
+
+    /// Number of outputs with each bit set.
+    pub ones: [u64; 64],
+
+    /// Number of times each bit differed from the previous output.
+    pub flips: [u64; 64],
+}
+
+impl BitStats {
+    /// Counts the top `bits` bits of `words` consecutive outputs from `rng`.
+    pub fn from_rng(rng: &mut dyn RngCore, words: u64, bits: u32) -> Self {
+        let mut stats = Self {
+            words: 0,
+            bits: bits.min(u64::BITS),
+            ones: [0; 64],
+            flips: [0; 64],
+        };
+        let mut previous = None;
+
+        for _ in 0..words {
+            let word = rng.next_u64();
+
+            for bit in stats.counted() {
+                stats.ones[bit] += (word >> bit) & 1;
+            }
+
+            if let Some(previous) = previous {
+                let changed = word ^ previous;
+
+                for bit in stats.counted() {
+                    stats.flips[bit] += (changed >> bit) & 1;
+                }
+            }
+
+            previous = Some(word);
+            stats.words += 1;
+        }
+
+        stats
+    }
+
+    /// Splits `words` consecutive outputs from `rng` into `blocks` blocks, and counts the top `bits`
+    /// bits of each block separately.
+    pub fn blocks_from_rng(rng: &mut dyn RngCore, words: u64, blocks: u64, bits: u32) -> Vec<Self> {
+        let blocks = blocks.clamp(1, words.max(1));
+
+        (0..blocks)
+            .map(|block| {
+                let start = block * words / blocks;
+                let end = (block + 1) * words / blocks;
+                Self::from_rng(rng, end - start, bits)
+            })
+            .collect()
+    }
+
+    /// The bits that are counted, least significant first.
+    pub fn counted(&self) -> Range<usize> {
+        (u64::BITS - self.bits) as usize..u64::BITS as usize
+    }
+
+    /// Fraction of outputs with `bit` set, which should be close to a half.
+    pub fn ones_frequency(&self, bit: usize) -> f64 {
+        self.ones[bit] as f64 / self.words as f64
+    }
+
+    /// Fraction of consecutive outputs where `bit` changed, which should also be close to a half.
+    pub fn flip_probability(&self, bit: usize) -> f64 {
+        self.flips[bit] as f64 / (self.words - 1) as f64
+    }
+
+    /// How many standard deviations the number of outputs with `bit` set is from half of them.
+    pub fn ones_z_score(&self, bit: usize) -> f64 {
+        z_score(self.ones[bit], self.words)
+    }
+
+    /// How many standard deviations the number of times `bit` changed is from half of the chances
+    /// it had to.
+    pub fn flips_z_score(&self, bit: usize) -> f64 {
+        z_score(self.flips[bit], self.words.saturating_sub(1))
+    }
+}
+
+/// z-score of `count` successes out of `trials` fair coin flips.
+fn z_score(count: u64, trials: u64) -> f64 {
+    if trials == 0 {
+        return 0.;
+    }
+
+    (count as f64 - trials as f64 / 2.) / (trials as f64 / 4.).sqrt()
+}
+
+/// Tests that every counted bit is set, and changes between outputs, half of the time.
+///
+/// The statistic is the sum of the squared z-scores of the ones and flips counts for each bit, which
+/// is roughly chi-squared distributed with twice as many degrees of freedom as there are bits. The
+/// two counts for each bit are close to independent, so this is a good approximation for a random
+/// source.
+pub fn bit_bias(stats: &BitStats) -> Result<TestResult, Error> {
+    if stats.words < 2 {
+        return Err(anyhow!("Bit bias test needs at least two outputs"));
+    }
+
+    if stats.bits == 0 {
+        return Err(anyhow!("Bit bias test needs at least one bit"));
+    }
+
+    let statistic = stats
+        .counted()
+        .map(|bit| stats.ones_z_score(bit).powi(2) + stats.flips_z_score(bit).powi(2))
+        .sum();
+
+    let degrees_of_freedom = 2 * stats.bits as u64;
+    let distribution = ChiSquared::new(degrees_of_freedom as f64)?;
+
+    Ok(TestResult {
+        name: "bit bias",
+        statistic,
+        degrees_of_freedom: Some(degrees_of_freedom),
+        p_value: distribution.sf(statistic),
+        two_sided: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::impls;
+
+    /// Returns each of a fixed list of words in turn.
+    struct Words(std::vec::IntoIter<u64>);
+
+    impl RngCore for Words {
+        fn next_u32(&mut self) -> u32 {
+            self.next_u64() as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.0.next().unwrap()
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            impls::fill_bytes_via_next(self, dest)
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn counts_ones_and_flips_of_the_top_bits() {
+        // The lowest bit is padding, so isn't counted even though it's set
+        let mut rng = Words(vec![0b1111 << 60 | 1, 0b0011 << 60, 0b0101 << 60].into_iter());
+        let stats = BitStats::from_rng(&mut rng, 3, 4);
+
+        assert_eq!(stats.counted(), 60..64);
+        assert_eq!(stats.ones[60..], [3, 2, 2, 1]);
+        assert_eq!(stats.flips[60..], [0, 1, 2, 1]);
+        assert_eq!(stats.ones[0], 0);
+        assert_eq!(stats.ones_frequency(61), 2. / 3.);
+        assert_eq!(stats.flip_probability(62), 1.);
+
+        assert!((stats.ones_z_score(60) - 3f64.sqrt()).abs() < 1e-12);
+        assert!((stats.flips_z_score(62) - 2f64.sqrt()).abs() < 1e-12);
+        assert_eq!(bit_bias(&stats).unwrap().degrees_of_freedom, Some(8));
+    }
+
+    #[test]
+    fn z_score_counts_standard_deviations_from_half() {
+        assert_eq!(z_score(50, 100), 0.);
+        assert_eq!(z_score(60, 100), 2.);
+        assert_eq!(z_score(1, 4), -1.);
+        assert_eq!(z_score(0, 0), 0.);
+    }
+}
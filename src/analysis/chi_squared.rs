@@ -0,0 +1,44 @@
+use anyhow::{anyhow, Error};
+use statrs::distribution::{ChiSquared, ContinuousCDF};
+
+use crate::analysis::{check_points, histogram, Bin, TestResult};
+
+/// Pearson's chi-squared goodness of fit test of `points` against the uniform distribution over
+/// `0..range`.
+///
+/// The range is split into `bins` bins as for [`histogram`].
+pub fn chi_squared(points: &[u64], range: u64, bins: usize) -> Result<TestResult, Error> {
+    check_points(points, range)?;
+
+    chi_squared_of_histogram(&histogram(points, range, bins), range)
+}
+
+/// The same test as [`chi_squared`], on the counts in a histogram over `0..range` that's already
+/// been filled in.
+pub fn chi_squared_of_histogram(histogram: &[Bin], range: u64) -> Result<TestResult, Error> {
+    if histogram.len() < 2 {
+        return Err(anyhow!("Chi-squared test needs at least two bins"));
+    }
+
+    let point_count = histogram.iter().map(|bin| bin.count).sum::<u64>() as f64;
+
+    let statistic = histogram
+        .iter()
+        .map(|bin| {
+            let expected = point_count * bin.width as f64 / range as f64;
+
+            (bin.count as f64 - expected).powi(2) / expected
+        })
+        .sum();
+
+    let degrees_of_freedom = histogram.len() as u64 - 1;
+    let distribution = ChiSquared::new(degrees_of_freedom as f64)?;
+
+    Ok(TestResult {
+        name: "chi-squared",
+        statistic,
+        degrees_of_freedom: Some(degrees_of_freedom),
+        p_value: distribution.sf(statistic),
+        two_sided: true,
+    })
+}
@@ -0,0 +1,155 @@
+use anyhow::{anyhow, Error};
+use statrs::distribution::{ChiSquared, ContinuousCDF, Normal};
+
+use crate::analysis::{check_points, TestResult};
+
+/// Sample autocorrelation of `points` at each lag from 1 up to and including `max_lag`.
+///
+/// The first entry is the correlation between each point and the one straight after it, the second
+/// between each point and the one after that, and so on. Lags as long as the sample are dropped.
+pub fn autocorrelation(points: &[u64], max_lag: usize) -> Vec<f64> {
+    let n = points.len();
+    let mean = points.iter().map(|val| *val as f64).sum::<f64>() / n as f64;
+    let deviations = points
+        .iter()
+        .map(|val| *val as f64 - mean)
+        .collect::<Vec<_>>();
+    let variance: f64 = deviations.iter().map(|d| d * d).sum();
+
+    (1..=max_lag.min(n.saturating_sub(1)))
+        .map(|lag| {
+            if variance == 0. {
+                return 0.;
+            }
+
+            deviations
+                .iter()
+                .zip(&deviations[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f64>()
+                / variance
+        })
+        .collect()
+}
+
+/// Half width of the band around zero that autocorrelations from `point_count` truly random points
+/// fall within 95% of the time.
+pub fn autocorrelation_bound(point_count: usize) -> f64 {
+    1.96 / (point_count as f64).sqrt()
+}
+
+/// Ljung-Box test for autocorrelation at any lag from 1 up to and including `max_lag`.
+///
+/// Lags past a quarter of the sample are left out, as the statistic no longer follows the
+/// chi-squared distribution once the lags get close to the sample size.
+pub fn ljung_box(points: &[u64], range: u64, max_lag: usize) -> Result<TestResult, Error> {
+    check_points(points, range)?;
+
+    let acf = autocorrelation(points, max_lag.min((points.len() / 4).max(1)));
+
+    if acf.is_empty() {
+        return Err(anyhow!("Ljung-Box test needs at least one lag"));
+    }
+
+    let n = points.len() as f64;
+    let statistic = n
+        * (n + 2.)
+        * acf
+            .iter()
+            .enumerate()
+            .map(|(i, r)| r * r / (n - (i + 1) as f64))
+            .sum::<f64>();
+
+    let degrees_of_freedom = acf.len() as u64;
+    let distribution = ChiSquared::new(degrees_of_freedom as f64)?;
+
+    Ok(TestResult {
+        name: "ljung-box",
+        statistic,
+        degrees_of_freedom: Some(degrees_of_freedom),
+        p_value: distribution.sf(statistic),
+        two_sided: true,
+    })
+}
+
+/// Wald-Wolfowitz runs test, counting runs of points above and below the middle of the range.
+///
+/// Splitting at the middle of the range rather than the sample's median means no point is ever
+/// tied with it and dropped, which matters for small ranges. When the range is odd the middle value
+/// counts as below. The statistic is the normal approximation's z-score, so too few runs gives a
+/// large negative value and too many a large positive one. Its p-value already covers both, so
+/// one close to 1 just means the count of runs was about right. If every point is on the same side
+/// there's only one run, which fails outright.
+pub fn runs(points: &[u64], range: u64) -> Result<TestResult, Error> {
+    check_points(points, range)?;
+
+    let signs = points
+        .iter()
+        .map(|val| 2 * *val as u128 >= range as u128)
+        .collect::<Vec<_>>();
+
+    let above = signs.iter().filter(|above| **above).count() as f64;
+    let below = signs.len() as f64 - above;
+
+    if above == 0. || below == 0. {
+        return Ok(TestResult {
+            name: "runs",
+            statistic: f64::NEG_INFINITY,
+            degrees_of_freedom: None,
+            p_value: 0.,
+            two_sided: false,
+        });
+    }
+
+    let runs = 1 + signs.windows(2).filter(|pair| pair[0] != pair[1]).count();
+
+    let n = above + below;
+    let expected = 2. * above * below / n + 1.;
+    let variance = (expected - 1.) * (expected - 2.) / (n - 1.);
+    let statistic = (runs as f64 - expected) / variance.sqrt();
+
+    let normal = Normal::new(0., 1.)?;
+
+    Ok(TestResult {
+        name: "runs",
+        statistic,
+        degrees_of_freedom: None,
+        p_value: 2. * normal.sf(statistic.abs()),
+        two_sided: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_splits_at_the_middle_of_the_range() {
+        // Alternating between the halves of 0..2 gives as many runs as there can be
+        let alternating = runs(&[0, 1, 0, 1, 0, 1, 0, 1], 2).unwrap();
+        assert!(alternating.statistic > 0. && alternating.statistic.is_finite());
+
+        // The middle of an odd range counts as below, so this is a single run
+        let single = runs(&[0, 1, 1, 0], 3).unwrap();
+        assert_eq!(single.statistic, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn runs_passes_the_expected_number_of_runs() {
+        // Six points split evenly have four runs expected, so exactly four gives a p-value of 1
+        let result = runs(&[0, 0, 1, 0, 1, 1], 2).unwrap();
+        assert!((result.p_value - 1.).abs() < 1e-12);
+        assert!(result.passed(0.01));
+    }
+
+    #[test]
+    fn ljung_box_caps_lags_at_a_quarter_of_the_sample() {
+        let points = (0..40).map(|i| i * 7 % 10).collect::<Vec<_>>();
+        let result = ljung_box(&points, 10, 30).unwrap();
+        assert_eq!(result.degrees_of_freedom, Some(10));
+
+        // Even the shortest samples keep one lag
+        let result = ljung_box(&[0, 1, 2], 3, 20).unwrap();
+        assert_eq!(result.degrees_of_freedom, Some(1));
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Error;
+use itertools::Itertools;
+
+use crate::analysis::{check_points, TestResult};
+
+/// One-sample Kolmogorov-Smirnov test of `points` against the discrete uniform distribution over
+/// `0..range`.
+///
+/// The p-value comes from the asymptotic Kolmogorov distribution, which is conservative for
+/// discrete distributions: the smaller the range, the closer to 1 the p-values of truly random
+/// samples get. So only a small p-value fails, and small ranges pass more easily than they should.
+pub fn kolmogorov_smirnov(points: &[u64], range: u64) -> Result<TestResult, Error> {
+    check_points(points, range)?;
+
+    let n = points.len() as f64;
+    let mut below = 0;
+    let mut statistic: f64 = 0.;
+
+    // Walk through each distinct value, comparing the empirical and expected CDFs either side of it
+    for (val, count) in points
+        .iter()
+        .sorted()
+        .dedup_with_count()
+        .map(|(c, v)| (*v, c))
+    {
+        let empirical_before = below as f64 / n;
+        let empirical_after = (below + count) as f64 / n;
+
+        statistic = statistic
+            .max((empirical_before - val as f64 / range as f64).abs())
+            .max((empirical_after - (val + 1) as f64 / range as f64).abs());
+
+        below += count;
+    }
+
+    Ok(TestResult {
+        name: "kolmogorov-smirnov",
+        statistic,
+        degrees_of_freedom: None,
+        p_value: kolmogorov_sf(stephens_lambda(n, statistic)),
+        two_sided: false,
+    })
+}
+
+/// Scales `statistic` from `n` samples by Stephens' small sample correction, so it can be compared
+/// against the asymptotic distribution.
+fn stephens_lambda(n: f64, statistic: f64) -> f64 {
+    (n.sqrt() + 0.12 + 0.11 / n.sqrt()) * statistic
+}
+
+/// Survival function of the Kolmogorov distribution.
+fn kolmogorov_sf(lambda: f64) -> f64 {
+    // The series converges too slowly to be useful this close to zero, where the answer is 1 anyway
+    if lambda < 0.2 {
+        return 1.;
+    }
+
+    let mut sum = 0.;
+
+    for j in 1..=100 {
+        let term = (-2. * (j * j) as f64 * lambda * lambda).exp();
+        sum += if j % 2 == 1 { term } else { -term };
+
+        if term < 1e-12 {
+            break;
+        }
+    }
+
+    (2. * sum).clamp(0., 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kolmogorov_sf_matches_known_percentiles() {
+        assert!((kolmogorov_sf(1.358) - 0.05).abs() < 1e-3);
+        assert!((kolmogorov_sf(1.628) - 0.01).abs() < 1e-3);
+        assert_eq!(kolmogorov_sf(0.1), 1.);
+    }
+
+    #[test]
+    fn stephens_correction_matches_exact_critical_values() {
+        // Exact 5% and 1% critical values of the statistic for 10 samples
+        for (statistic, p) in [(0.40925, 0.05), (0.48893, 0.01)] {
+            let corrected = kolmogorov_sf(stephens_lambda(10., statistic));
+            assert!((corrected - p).abs() < 2e-3, "{}", corrected);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! Statistical tests on the samples drawn from a generator.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Instant;
+
+use anyhow::{anyhow, Error};
+use log::{debug, info};
+use serde::Deserialize;
+
+pub mod anderson_darling;
+pub mod bits;
+pub mod chi_squared;
+pub mod correlation;
+pub mod kolmogorov_smirnov;
+
+/// Settings shared by the statistical tests.
+#[derive(Clone, Debug)]
+pub struct AnalysisOptions {
+    /// Number of bins to split the range into for binned tests.
+    pub bins: usize,
+
+    /// Tests with a p-value below this, or for two-sided tests above one minus this, fail.
+    pub significance: f64,
+
+    /// Longest lag to look for autocorrelation at.
+    pub max_lag: usize,
+
+    /// Which tests to run.
+    pub tests: Vec<Test>,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            bins: 100,
+            significance: 0.01,
+            max_lag: 20,
+            tests: Test::ALL.to_vec(),
+        }
+    }
+}
+
+impl AnalysisOptions {
+    /// Whether `test` is one of the tests to run.
+    pub fn runs(&self, test: Test) -> bool {
+        self.tests.contains(&test)
+    }
+}
+
+/// A statistical test that can be chosen to run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Test {
+    ChiSquared,
+    KolmogorovSmirnov,
+    AndersonDarling,
+    LjungBox,
+    Runs,
+    BitBias,
+}
+
+impl Test {
+    /// Every test, in the order they're run and reported in.
+    pub const ALL: [Self; 6] = [
+        Self::ChiSquared,
+        Self::KolmogorovSmirnov,
+        Self::AndersonDarling,
+        Self::LjungBox,
+        Self::Runs,
+        Self::BitBias,
+    ];
+}
+
+impl FromStr for Test {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chi-squared" => Ok(Self::ChiSquared),
+            "kolmogorov-smirnov" => Ok(Self::KolmogorovSmirnov),
+            "anderson-darling" => Ok(Self::AndersonDarling),
+            "ljung-box" => Ok(Self::LjungBox),
+            "runs" => Ok(Self::Runs),
+            "bit-bias" => Ok(Self::BitBias),
+            _ => Err(anyhow!(
+                "Unknown test '{}', expected chi-squared, kolmogorov-smirnov, anderson-darling, \
+                 ljung-box, runs or bit-bias",
+                s
+            )),
+        }
+    }
+}
+
+/// The outcome of a single statistical test.
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    /// Name of the test that was run.
+    pub name: &'static str,
+
+    /// The test statistic.
+    pub statistic: f64,
+
+    /// Degrees of freedom of the test statistic's distribution, for tests that have them.
+    pub degrees_of_freedom: Option<u64>,
+
+    /// Probability of a statistic at least this extreme from a truly random source.
+    pub p_value: f64,
+
+    /// Whether a p-value very close to 1 fails as well as one very close to 0. Tests whose p-values
+    /// are only an upper bound, or already count deviations either way, can't tell a suspiciously
+    /// good fit from an ordinary one.
+    pub two_sided: bool,
+}
+
+impl TestResult {
+    /// Whether the p-value is within `significance` of neither 0 nor, for two-sided tests, 1.
+    ///
+    /// A p-value very close to 1 fails too, as it means the samples fit the expected distribution
+    /// better than random samples would, like the counting sequence does.
+    pub fn passed(&self, significance: f64) -> bool {
+        self.p_value >= significance && (!self.two_sided || self.p_value <= 1. - significance)
+    }
+}
+
+impl Display for TestResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<20} statistic {:>14.4}", self.name, self.statistic)?;
+
+        match self.degrees_of_freedom {
+            Some(degrees_of_freedom) => write!(f, "  df {:>8}", degrees_of_freedom)?,
+            None => write!(f, "  {:>11}", "")?,
+        }
+
+        write!(f, "  p {:.4}", self.p_value)
+    }
+}
+
+/// A bin of a histogram over `0..range`.
+#[derive(Clone, Copy, Debug)]
+pub struct Bin {
+    /// First value in the bin.
+    pub start: u64,
+
+    /// Number of values in the bin.
+    pub width: u64,
+
+    /// Number of points that landed in the bin.
+    pub count: u64,
+}
+
+/// Counts how many of `points` land in each of `bins` bins over `0..range`.
+///
+/// The bins are of as near equal width as possible, or there is one bin per value if the range is
+/// smaller than `bins`. Every point must lie in `0..range`.
+pub fn histogram(points: &[u64], range: u64, bins: usize) -> Vec<Bin> {
+    let mut histogram = empty_histogram(range, bins);
+    let bins = histogram.len();
+
+    for val in points {
+        histogram[bin_of(*val, range, bins)].count += 1;
+    }
+
+    histogram
+}
+
+/// The bins [`histogram`] would count points into, with nothing in them yet.
+pub fn empty_histogram(range: u64, bins: usize) -> Vec<Bin> {
+    let bins = bins.min(range as usize).max(1) as u64;
+
+    // The first value landing in each bin, so bins can be of slightly different widths
+    let bin_start = |bin: u64| (bin as u128 * range as u128).div_ceil(bins as u128) as u64;
+
+    (0..bins)
+        .map(|bin| Bin {
+            start: bin_start(bin),
+            width: bin_start(bin + 1) - bin_start(bin),
+            count: 0,
+        })
+        .collect()
+}
+
+/// Index of the bin `val` lands in, out of `bins` from [`empty_histogram`] over `0..range`.
+pub fn bin_of(val: u64, range: u64, bins: usize) -> usize {
+    (val as u128 * bins as u128 / range as u128) as usize
+}
+
+/// Runs each of the tests in `options` over `points`, which should be uniformly distributed over
+/// `0..range`. The bit bias test is left out, as it needs the generator's raw output instead.
+pub fn run(
+    points: &[u64],
+    range: u64,
+    options: &AnalysisOptions,
+) -> Result<Vec<TestResult>, Error> {
+    let start = Instant::now();
+
+    let results = Test::ALL
+        .into_iter()
+        .filter(|test| options.runs(*test))
+        .filter_map(|test| match test {
+            Test::ChiSquared => Some(chi_squared::chi_squared(points, range, options.bins)),
+            Test::KolmogorovSmirnov => Some(kolmogorov_smirnov::kolmogorov_smirnov(points, range)),
+            Test::AndersonDarling => Some(anderson_darling::anderson_darling(points, range)),
+            Test::LjungBox => Some(correlation::ljung_box(points, range, options.max_lag)),
+            Test::Runs => Some(correlation::runs(points, range)),
+            Test::BitBias => None,
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for result in &results {
+        debug!(
+            "{} ({})",
+            result,
+            if result.passed(options.significance) {
+                "pass"
+            } else {
+                "fail"
+            }
+        );
+    }
+
+    info!(
+        "Ran {} tests on {} points in {:.1?}",
+        results.len(),
+        points.len(),
+        start.elapsed()
+    );
+
+    Ok(results)
+}
+
+/// Checks there is something to test, and that every point lies in `0..range`.
+fn check_points(points: &[u64], range: u64) -> Result<(), Error> {
+    if points.is_empty() {
+        return Err(anyhow!("Statistical tests need at least one point"));
+    }
+
+    match points.iter().find(|val| **val >= range) {
+        Some(val) => Err(anyhow!("Point {} is outside the range 0..{}", val, range)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(p_value: f64, two_sided: bool) -> TestResult {
+        TestResult {
+            name: "test",
+            statistic: 0.,
+            degrees_of_freedom: None,
+            p_value,
+            two_sided,
+        }
+    }
+
+    #[test]
+    fn only_two_sided_tests_fail_near_one() {
+        assert!(!result(0.995, true).passed(0.01));
+        assert!(result(0.995, false).passed(0.01));
+        assert!(!result(0.005, false).passed(0.01));
+        assert!(result(0.5, true).passed(0.01));
+    }
+
+    #[test]
+    fn runs_only_the_chosen_tests() {
+        let options = AnalysisOptions {
+            tests: vec![Test::Runs, Test::BitBias, Test::ChiSquared],
+            ..Default::default()
+        };
+        let points = (0..100).map(|i| i * 37 % 100).collect::<Vec<_>>();
+        let names = run(&points, 100, &options)
+            .unwrap()
+            .iter()
+            .map(|result| result.name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["chi-squared", "runs"]);
+        assert_eq!("ljung-box".parse::<Test>().unwrap(), Test::LjungBox);
+        assert!("ljung".parse::<Test>().is_err());
+    }
+}
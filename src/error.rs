@@ -0,0 +1,40 @@
+//! Errors from drawing charts and writing them to disk.
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FitnessError {
+    /// There weren't enough points to draw anything.
+    #[error("Not enough points to draw {path}")]
+    EmptyInput { path: PathBuf },
+
+    /// plotlib couldn't turn a chart into an SVG.
+    #[error("Couldn't render {path}: {message}")]
+    Render { path: PathBuf, message: String },
+
+    /// An SVG couldn't be turned into a PNG.
+    #[error("Couldn't rasterise {path}")]
+    Rasterise {
+        path: PathBuf,
+        #[source]
+        source: charts_rs::EncoderError,
+    },
+
+    /// Pixels couldn't be encoded as a PNG.
+    #[error("Couldn't encode {path}")]
+    Encode {
+        path: PathBuf,
+        #[source]
+        source: png::EncodingError,
+    },
+
+    /// A file couldn't be written.
+    #[error("Couldn't write {path}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
@@ -0,0 +1,394 @@
+//! Experiments: which generators to run over which ranges and point counts, and how to analyse and
+//! draw the results. They can be read from TOML or YAML files as well as built up from the command
+//! line.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Error};
+use serde::Deserialize;
+
+use crate::analysis::{AnalysisOptions, Test};
+use crate::export::ExportFormat;
+use crate::generators::{GeneratorSpec, Registry, Selected};
+use crate::plot::style::{Style, StyleOverrides};
+use crate::plot::{Chart, OutputFormat};
+use crate::sweep::{self, Sweep};
+
+/// Range used when an experiment doesn't give any.
+pub const DEFAULT_RANGE: u64 = 10000;
+
+/// Number of points used when an experiment doesn't give any.
+pub const DEFAULT_POINTS: u64 = 10000;
+
+/// Everything needed to run a set of generators over a set of ranges and point counts.
+///
+/// Every field is optional. Empty lists and missing values fall back to the defaults, apart from
+/// `generators` which falls back to every generator in the registry.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Experiment {
+    /// Generators to run, as `name` or `name=seed`.
+    pub generators: Vec<GeneratorSpec>,
+
+    /// Seed for every seedable generator that isn't given its own.
+    pub seed: Option<u64>,
+
+    /// Upper bounds (exclusive) of the sampled values.
+    pub ranges: Vec<Sweep>,
+
+    /// Numbers of points to sample.
+    pub points: Vec<Sweep>,
+
+    pub analysis: AnalysisConfig,
+
+    pub style: StyleOverrides,
+
+    pub output: OutputConfig,
+}
+
+/// Settings for the statistical tests, falling back to [`AnalysisOptions::default`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalysisConfig {
+    pub bins: Option<usize>,
+    pub significance: Option<f64>,
+    pub max_lag: Option<usize>,
+
+    /// Tests to run, every one of them if empty.
+    pub tests: Vec<Test>,
+}
+
+impl AnalysisConfig {
+    pub fn options(&self) -> AnalysisOptions {
+        let defaults = AnalysisOptions::default();
+
+        AnalysisOptions {
+            bins: self.bins.unwrap_or(defaults.bins),
+            significance: self.significance.unwrap_or(defaults.significance),
+            max_lag: self.max_lag.unwrap_or(defaults.max_lag),
+            tests: non_empty_or(self.tests.clone(), defaults.tests),
+        }
+    }
+
+    /// Takes everything set here, falling back to `other` for anything that isn't.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            bins: self.bins.or(other.bins),
+            significance: self.significance.or(other.significance),
+            max_lag: self.max_lag.or(other.max_lag),
+            tests: non_empty_or(self.tests, other.tests),
+        }
+    }
+}
+
+/// Where and how to write output.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Directory each run's directory is created in.
+    pub dir: Option<PathBuf>,
+
+    /// Name of the run's directory.
+    pub run_id: Option<String>,
+
+    /// Which files to write each chart to, PNG by default.
+    pub format: Option<OutputFormat>,
+
+    /// Which kinds of file to export raw data to, if any.
+    pub export: Vec<ExportFormat>,
+
+    /// Charts to draw for each run, every one of them if empty.
+    pub charts: Vec<Chart>,
+}
+
+impl OutputConfig {
+    /// Takes everything set here, falling back to `other` for anything that isn't.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            dir: self.dir.or(other.dir),
+            run_id: self.run_id.or(other.run_id),
+            format: self.format.or(other.format),
+            export: non_empty_or(self.export, other.export),
+            charts: non_empty_or(self.charts, other.charts),
+        }
+    }
+
+    /// Whether `chart` is one of the charts to draw.
+    pub fn draws(&self, chart: Chart) -> bool {
+        self.charts.is_empty() || self.charts.contains(&chart)
+    }
+}
+
+/// A single generator run over a single range and point count.
+#[derive(Clone, Copy)]
+pub struct Run<'a> {
+    pub selected: Selected<'a>,
+    pub range: u64,
+    pub point_count: u64,
+}
+
+impl Run<'_> {
+    /// Draws the samples for this run.
+    pub fn samples(&self) -> Vec<u64> {
+        self.selected.samples(self.range, self.point_count)
+    }
+
+    /// Draws the samples for this run one at a time, without keeping them.
+    pub fn stream(&self) -> Box<dyn Iterator<Item = u64>> {
+        self.selected.stream(self.range, self.point_count)
+    }
+
+    /// File name, without extension, used for this run's output.
+    pub fn file_stem(&self) -> String {
+        self.selected.file_stem(self.range, self.point_count)
+    }
+
+    /// Key/value pairs describing this run, for embedding in output.
+    pub fn metadata(&self) -> Vec<(&'static str, String)> {
+        self.selected.metadata(self.range, self.point_count)
+    }
+}
+
+impl Experiment {
+    /// Reads an experiment from a TOML or YAML file, going by its extension.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read experiment file {}", path.display()))?;
+        let invalid = || format!("Invalid experiment file {}", path.display());
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => toml::from_str(&text).with_context(invalid),
+            Some("yaml" | "yml") => serde_yaml::from_str(&text).with_context(invalid),
+            _ => Err(anyhow!(
+                "Experiment file {} should end in .toml, .yaml or .yml",
+                path.display()
+            )),
+        }
+    }
+
+    /// Takes everything set here, falling back to `other` for anything that isn't. Lists are
+    /// replaced as a whole rather than merged.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            generators: non_empty_or(self.generators, other.generators),
+            seed: self.seed.or(other.seed),
+            ranges: non_empty_or(self.ranges, other.ranges),
+            points: non_empty_or(self.points, other.points),
+            analysis: self.analysis.or(other.analysis),
+            style: self.style.or(other.style),
+            output: self.output.or(other.output),
+        }
+    }
+
+    /// Ranges to run over, or just [`DEFAULT_RANGE`] if there aren't any.
+    pub fn ranges(&self) -> Vec<u64> {
+        non_empty_or(sweep::expand(&self.ranges), vec![DEFAULT_RANGE])
+    }
+
+    /// Point counts to run over, or just [`DEFAULT_POINTS`] if there aren't any.
+    pub fn point_counts(&self) -> Vec<u64> {
+        non_empty_or(sweep::expand(&self.points), vec![DEFAULT_POINTS])
+    }
+
+    /// The chart style, from the theme and overrides in `style`.
+    pub fn chart_style(&self) -> Result<Style, Error> {
+        Style::from_overrides(&self.style).map_err(|e| anyhow!("style: {}", e))
+    }
+
+    /// Checks every setting makes sense, naming the key at fault if one doesn't.
+    pub fn validate(&self, registry: &Registry) -> Result<(), Error> {
+        for (i, spec) in self.generators.iter().enumerate() {
+            registry
+                .select(std::slice::from_ref(spec), self.seed)
+                .map_err(|e| anyhow!("generators[{}]: {}", i, e))?;
+        }
+
+        // The tests need two values to tell apart, and the lag plots and tests at least three
+        // points in a row
+        for (key, sweeps, min) in [("ranges", &self.ranges, 2), ("points", &self.points, 3)] {
+            for (i, sweep) in sweeps.iter().enumerate() {
+                sweep
+                    .validate(min)
+                    .map_err(|e| anyhow!("{}[{}]: {}", key, i, e))?;
+            }
+        }
+
+        let analysis = self.analysis.options();
+
+        // A single bin leaves the chi-squared test nothing to compare
+        if analysis.bins < 2 {
+            return Err(anyhow!("analysis.bins: must be at least 2"));
+        }
+
+        if !(analysis.significance > 0. && analysis.significance < 0.5) {
+            return Err(anyhow!(
+                "analysis.significance: must be between 0 and 0.5, not {}",
+                analysis.significance
+            ));
+        }
+
+        if analysis.max_lag == 0 {
+            return Err(anyhow!("analysis.max_lag: must be at least 1"));
+        }
+
+        self.chart_style()?;
+
+        Ok(())
+    }
+
+    /// Every generator over every range and point count, ordered by range, then point count, then
+    /// generator.
+    pub fn runs<'a>(&self, registry: &'a Registry) -> Result<Vec<Run<'a>>, Error> {
+        let selected = registry.select(&self.generators, self.seed)?;
+        let point_counts = self.point_counts();
+
+        Ok(self
+            .ranges()
+            .into_iter()
+            .flat_map(|range| {
+                let selected = &selected;
+
+                point_counts.iter().flat_map(move |point_count| {
+                    selected.iter().map(move |selected| Run {
+                        selected: *selected,
+                        range,
+                        point_count: *point_count,
+                    })
+                })
+            })
+            .collect())
+    }
+}
+
+fn non_empty_or<T>(list: Vec<T>, other: Vec<T>) -> Vec<T> {
+    if list.is_empty() {
+        other
+    } else {
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn or_prefers_what_is_set_here() {
+        let command_line = Experiment {
+            seed: Some(1),
+            points: vec!["500".parse().unwrap()],
+            analysis: AnalysisConfig {
+                bins: Some(20),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let file: Experiment = toml::from_str(
+            r#"
+            seed = 2
+            ranges = [100]
+            points = [1000, 2000]
+
+            [analysis]
+            bins = 50
+            max_lag = 5
+            tests = ["runs"]
+            "#,
+        )
+        .unwrap();
+
+        let experiment = command_line.or(file);
+
+        assert_eq!(experiment.seed, Some(1));
+        assert_eq!(experiment.point_counts(), [500]);
+        assert_eq!(experiment.ranges(), [100]);
+        assert_eq!(experiment.analysis.bins, Some(20));
+        assert_eq!(experiment.analysis.max_lag, Some(5));
+        assert_eq!(experiment.analysis.options().tests, [Test::Runs]);
+    }
+
+    #[test]
+    fn validate_names_the_key_at_fault() {
+        let registry = Registry::default();
+        let error = |text: &str| {
+            toml::from_str::<Experiment>(text)
+                .unwrap()
+                .validate(&registry)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert!(error(r#"generators = ["pcg32", "pgc32"]"#).starts_with("generators[1]: "));
+        assert!(error("ranges = [100, 1]").starts_with("ranges[1]: "));
+        assert!(error("points = [2]").starts_with("points[0]: "));
+        assert_eq!(
+            error("analysis.bins = 1"),
+            "analysis.bins: must be at least 2"
+        );
+        assert!(error("analysis.significance = 0.5").starts_with("analysis.significance: "));
+        assert!(error("analysis.max_lag = 0").starts_with("analysis.max_lag: "));
+        assert!(error("style.width = 0").starts_with("style: "));
+
+        assert!(Experiment::default().validate(&registry).is_ok());
+    }
+
+    #[test]
+    fn reads_toml_and_yaml_alike() {
+        let dir = std::env::temp_dir().join(format!(
+            "procedural_fitness_experiment_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let toml_path = dir.join("experiment.toml");
+        std::fs::write(
+            &toml_path,
+            r#"
+            generators = ["pcg32=1", { name = "randu", seed = 7 }]
+            ranges = [{ start = 10, end = 1000, factor = 10 }]
+
+            [analysis]
+            tests = ["chi-squared", "bit-bias"]
+
+            [output]
+            format = "svg"
+            charts = ["series", "lag2d"]
+            "#,
+        )
+        .unwrap();
+
+        let yaml_path = dir.join("experiment.yaml");
+        std::fs::write(
+            &yaml_path,
+            "generators: [pcg32=1, { name: randu, seed: 7 }]\n\
+             ranges: [{ start: 10, end: 1000, factor: 10 }]\n\
+             analysis:\n  tests: [chi-squared, bit-bias]\n\
+             output:\n  format: svg\n  charts: [series, lag2d]\n",
+        )
+        .unwrap();
+
+        for path in [&toml_path, &yaml_path] {
+            let experiment = Experiment::from_file(path).unwrap();
+            let registry = Registry::default();
+            let labels = experiment
+                .runs(&registry)
+                .unwrap()
+                .iter()
+                .map(|run| run.file_stem())
+                .collect::<Vec<_>>();
+
+            assert_eq!(labels.len(), 6, "{}", path.display());
+            assert_eq!(labels[0], "pcg32_seed1_10_10000_rng");
+            assert_eq!(labels[1], "randu_seed7_10_10000_rng");
+            assert_eq!(experiment.ranges(), [10, 100, 1000]);
+            assert_eq!(experiment.analysis.tests, [Test::ChiSquared, Test::BitBias]);
+            assert_eq!(experiment.output.format, Some(OutputFormat::Svg));
+            assert!(experiment.output.draws(Chart::Lag2d));
+            assert!(!experiment.output.draws(Chart::Noise));
+        }
+
+        assert!(Experiment::from_file(&dir.join("experiment.json")).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,218 @@
+//! Writes the numbers behind each chart to files, so they can be reloaded elsewhere.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::TestResult;
+use crate::fitness::{Fitness, WalkStatistic};
+
+/// A kind of file to export data to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow!(
+                "Unknown export format '{}', expected csv or json",
+                s
+            )),
+        }
+    }
+}
+
+/// Everything worked out for one set of samples.
+pub struct RunData<'a> {
+    /// Key/value pairs describing the run, as stored in the PNGs.
+    pub metadata: &'a [(&'static str, String)],
+
+    /// The samples themselves.
+    pub points: &'a [u64],
+
+    /// Results of the statistical tests.
+    pub results: &'a [TestResult],
+
+    /// Significance the tests were judged at.
+    pub significance: f64,
+
+    /// The fitness walk and its statistics.
+    pub fitness: &'a Fitness,
+}
+
+impl RunData<'_> {
+    /// Named fitness walk statistics, in the order they're printed.
+    fn walk_statistics(&self) -> [(&'static str, &WalkStatistic); 4] {
+        [
+            ("max_excursion", &self.fitness.max_excursion),
+            ("final_displacement", &self.fitness.final_displacement),
+            ("zero_crossings", &self.fitness.zero_crossings),
+            (
+                "longest_monotone_stretch",
+                &self.fitness.longest_monotone_stretch,
+            ),
+        ]
+    }
+
+    /// Writes the data in `format` to files named after `full_path`.
+    pub fn export(&self, full_path: &Path, format: ExportFormat) -> Result<(), Error> {
+        match format {
+            ExportFormat::Csv => self.write_csv(full_path),
+            ExportFormat::Json => self.write_json(full_path),
+        }
+    }
+
+    /// Writes three CSV files next to `full_path`: `_samples.csv` with each sample and the fitness
+    /// walk at that point, `_tests.csv` with a row per test, and `_fitness.csv` with a row per walk
+    /// statistic and a final row for the score.
+    pub fn write_csv(&self, full_path: &Path) -> Result<(), Error> {
+        let mut samples = String::from("index,value,fitness_walk\n");
+
+        for (i, (value, walk)) in self.points.iter().zip(&self.fitness.walk).enumerate() {
+            writeln!(samples, "{},{},{}", i, value, walk)?;
+        }
+
+        let mut tests = String::from("test,statistic,degrees_of_freedom,p_value,passed\n");
+
+        for result in self.results {
+            writeln!(
+                tests,
+                "{},{},{},{},{}",
+                result.name,
+                result.statistic,
+                result
+                    .degrees_of_freedom
+                    .map_or(String::new(), |df| df.to_string()),
+                result.p_value,
+                result.passed(self.significance)
+            )?;
+        }
+
+        let mut fitness = String::from("statistic,value,expected,std_dev,z_score\n");
+
+        for (name, statistic) in self.walk_statistics() {
+            writeln!(
+                fitness,
+                "{},{},{},{},{}",
+                name,
+                statistic.value,
+                statistic.expected,
+                statistic.std_dev,
+                statistic.z_score()
+            )?;
+        }
+
+        writeln!(fitness, "score,{},,,", self.fitness.score())?;
+
+        for (suffix, contents) in [
+            ("_samples.csv", samples),
+            ("_tests.csv", tests),
+            ("_fitness.csv", fitness),
+        ] {
+            let path = with_suffix(full_path, suffix);
+            std::fs::write(&path, contents)?;
+            info!("Wrote {}", path.display());
+        }
+
+        Ok(())
+    }
+
+    /// Writes everything to a single JSON file at `full_path`. Infinite or undefined statistics are
+    /// written as `null`.
+    pub fn write_json(&self, full_path: &Path) -> Result<(), Error> {
+        let document = JsonRun {
+            metadata: self
+                .metadata
+                .iter()
+                .map(|(key, value)| (*key, value.as_str()))
+                .collect(),
+            samples: self.points,
+            fitness_walk: &self.fitness.walk,
+            tests: self
+                .results
+                .iter()
+                .map(|result| JsonTest {
+                    name: result.name,
+                    statistic: result.statistic,
+                    degrees_of_freedom: result.degrees_of_freedom,
+                    p_value: result.p_value,
+                    passed: result.passed(self.significance),
+                })
+                .collect(),
+            significance: self.significance,
+            fitness: self
+                .walk_statistics()
+                .into_iter()
+                .map(|(name, statistic)| {
+                    (
+                        name,
+                        JsonWalkStatistic {
+                            value: statistic.value,
+                            expected: statistic.expected,
+                            std_dev: statistic.std_dev,
+                            z_score: statistic.z_score(),
+                        },
+                    )
+                })
+                .collect(),
+            score: self.fitness.score(),
+        };
+
+        let path = full_path.with_extension("json");
+        let file = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(file, &document)?;
+        info!("Wrote {}", path.display());
+
+        Ok(())
+    }
+}
+
+/// Appends `suffix` to the file name of `full_path`.
+fn with_suffix(full_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = full_path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    full_path.with_file_name(name)
+}
+
+#[derive(Serialize)]
+struct JsonRun<'a> {
+    metadata: BTreeMap<&'static str, &'a str>,
+    samples: &'a [u64],
+    fitness_walk: &'a [i64],
+    tests: Vec<JsonTest>,
+    significance: f64,
+    fitness: BTreeMap<&'static str, JsonWalkStatistic>,
+    score: f64,
+}
+
+#[derive(Serialize)]
+struct JsonTest {
+    name: &'static str,
+    statistic: f64,
+    degrees_of_freedom: Option<u64>,
+    p_value: f64,
+    passed: bool,
+}
+
+#[derive(Serialize)]
+struct JsonWalkStatistic {
+    value: f64,
+    expected: f64,
+    std_dev: f64,
+    z_score: f64,
+}
@@ -0,0 +1,352 @@
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+use std::fmt::{Display, Formatter};
+
+/// Catalan's constant, which turns up in the second moment of a random walk's largest excursion.
+const CATALAN: f64 = 0.915_965_594_177_219;
+
+/// Computes the fitness walk for a sequence of points.
+///
+/// The walk starts halfway up the range of the points and steps up by one whenever a point is
+/// larger than the one before it, and down by one whenever it is smaller. A well distributed
+/// sequence should wander about without drifting too far from where it started.
+pub fn fitness_walk(points: &[u64]) -> Vec<i64> {
+    let mut current_fitness: i64 = points.iter().max().map_or(0, |max_y| *max_y as i64 / 2);
+    let mut last_val = points.first().map_or(0, |val| *val as i64);
+
+    points
+        .iter()
+        .map(|val| {
+            let val = *val as i64;
+            if val != last_val {
+                current_fitness += (val - last_val) / (val - last_val).abs()
+            };
+            last_val = val;
+            current_fitness
+        })
+        .collect()
+}
+
+/// A summary statistic of a fitness walk, along with what it would be for a truly random source.
+#[derive(Clone, Copy, Debug)]
+pub struct WalkStatistic {
+    pub value: f64,
+    pub expected: f64,
+    pub std_dev: f64,
+}
+
+impl WalkStatistic {
+    /// How many standard deviations the value is from what's expected.
+    pub fn z_score(&self) -> f64 {
+        if self.std_dev == 0. {
+            0.
+        } else {
+            (self.value - self.expected) / self.std_dev
+        }
+    }
+}
+
+impl Display for WalkStatistic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>8} (expected {:>8.1}, z {:>7.2})",
+            self.value,
+            self.expected,
+            self.z_score()
+        )
+    }
+}
+
+/// The fitness walk of a set of points, and how it compares with the walk of a truly random source.
+#[derive(Clone, Debug)]
+pub struct Fitness {
+    /// The walk itself, as returned from [`fitness_walk`]. Empty when the points were streamed
+    /// rather than kept.
+    pub walk: Vec<i64>,
+
+    /// Furthest the walk gets from where it started, in either direction.
+    pub max_excursion: WalkStatistic,
+
+    /// How far from where it started the walk ends up.
+    pub final_displacement: WalkStatistic,
+
+    /// Number of times the walk comes back to where it started.
+    pub zero_crossings: WalkStatistic,
+
+    /// Most steps taken in a row in the same direction.
+    pub longest_monotone_stretch: WalkStatistic,
+}
+
+impl Fitness {
+    /// Root mean square of the z-scores of each statistic. A truly random source should score
+    /// around 1, and the higher the score the worse the source.
+    pub fn score(&self) -> f64 {
+        let z_scores = [
+            self.max_excursion.z_score(),
+            self.final_displacement.z_score(),
+            self.zero_crossings.z_score(),
+            self.longest_monotone_stretch.z_score(),
+        ];
+
+        (z_scores.iter().map(|z| z * z).sum::<f64>() / z_scores.len() as f64).sqrt()
+    }
+}
+
+/// Computes the fitness walk of `points`, which should be uniformly distributed over `0..range`,
+/// along with its summary statistics.
+///
+/// The statistics are worked out by a [`WalkTracker`], so they're the same as for the points
+/// streamed one at a time.
+pub fn fitness(points: &[u64], range: u64) -> Fitness {
+    let mut tracker = WalkTracker::default();
+
+    for point in points {
+        tracker.push(*point);
+    }
+
+    tracker.fitness(range, fitness_walk(points))
+}
+
+/// Follows the fitness walk a point at a time, keeping only what's needed for its summary
+/// statistics rather than the walk itself.
+#[derive(Clone, Debug, Default)]
+pub struct WalkTracker {
+    points: u64,
+    last_point: u64,
+    displacement: i64,
+    max_excursion: i64,
+    zero_crossings: u64,
+    last_step: i64,
+    stretch: u64,
+    longest_stretch: u64,
+}
+
+impl WalkTracker {
+    /// Takes the next point, and returns how far the walk now is from where it started.
+    pub fn push(&mut self, point: u64) -> i64 {
+        if self.points > 0 {
+            let step = match point.cmp(&self.last_point) {
+                Ordering::Greater => 1,
+                Ordering::Less => -1,
+                Ordering::Equal => 0,
+            };
+
+            if self.displacement != 0 && self.displacement + step == 0 {
+                self.zero_crossings += 1;
+            }
+
+            self.displacement += step;
+            self.max_excursion = self.max_excursion.max(self.displacement.abs());
+
+            // Flat steps break a stretch
+            self.stretch = if step != 0 && step == self.last_step {
+                self.stretch + 1
+            } else if step != 0 {
+                1
+            } else {
+                0
+            };
+
+            self.longest_stretch = self.longest_stretch.max(self.stretch);
+            self.last_step = step;
+        }
+
+        self.last_point = point;
+        self.points += 1;
+
+        self.displacement
+    }
+
+    /// Number of points taken so far.
+    pub fn points(&self) -> u64 {
+        self.points
+    }
+
+    /// The summary statistics of the walk so far, for points that should be uniformly distributed
+    /// over `0..range`, along with `walk`, which is kept as given.
+    ///
+    /// The expected values assume independent uniform points. Consecutive steps of the walk from
+    /// such points aren't independent, as a step up makes the next step more likely to be down, so
+    /// the walk spreads out more slowly than a simple random walk. The expected final displacement
+    /// is exact, as is everything for a range of 2, where the walk can't get more than a step from
+    /// where it started. The rest are approximations that get better as the number of points grows,
+    /// and need more points the smaller the range is.
+    pub fn fitness(&self, range: u64, walk: Vec<i64>) -> Fitness {
+        let steps = self.points.saturating_sub(1) as f64;
+        let range = range.max(1) as f64;
+
+        // Each step is up or down with equal probability, or flat when two points are equal, and
+        // neighbouring steps have a correlation of -(range^2 - 1) / (3 range^2)
+        let step_variance = 1. - 1. / range;
+        let neighbour_covariance = -(range * range - 1.) / (3. * range * range);
+        let variance =
+            (steps * step_variance + 2. * (steps - 1.).max(0.) * neighbour_covariance).max(0.);
+
+        // Over enough steps the walk looks like Brownian motion with the same overall variance,
+        // whose largest excursion has known moments
+        let final_displacement = WalkStatistic {
+            value: self.displacement as f64,
+            expected: 0.,
+            std_dev: variance.sqrt(),
+        };
+
+        let max_excursion = if range <= 2. {
+            // The walk can't get further than one step away, and only stays put if every point
+            // is the same
+            let all_same = 0.5f64.powf(steps);
+
+            WalkStatistic {
+                value: self.max_excursion as f64,
+                expected: 1. - all_same,
+                std_dev: (all_same * (1. - all_same)).sqrt(),
+            }
+        } else {
+            WalkStatistic {
+                value: self.max_excursion as f64,
+                expected: (PI / 2. * variance).sqrt(),
+                std_dev: ((2. * CATALAN - PI / 2.) * variance).sqrt(),
+            }
+        };
+
+        let zero_crossings = WalkStatistic {
+            value: self.zero_crossings as f64,
+            ..expected_zero_crossings(steps, range)
+        };
+
+        let (expected, std_dev) = expected_longest_stretch(steps, range);
+
+        let longest_monotone_stretch = WalkStatistic {
+            value: self.longest_stretch as f64,
+            expected,
+            std_dev,
+        };
+
+        Fitness {
+            walk,
+            max_excursion,
+            final_displacement,
+            zero_crossings,
+            longest_monotone_stretch,
+        }
+    }
+}
+
+/// Expected number of returns to zero over `steps` steps between points uniformly distributed over
+/// `0..range`, and its standard deviation, with a value of 0.
+///
+/// With a range of 2 the walk is just how far the latest point is from the first, so it returns to
+/// zero whenever one particular pair of values comes up, which is worked out exactly. Otherwise the
+/// walk spends as long at zero as Brownian motion with the same variance, which is half-normally
+/// distributed, and each visit is a new return unless its step was flat. This is only a good
+/// approximation when `steps * (range - 1) * (range - 2) / (3 * range^2)`, the variance of the
+/// final displacement, is large, which takes a few hundred steps for a range of 3.
+fn expected_zero_crossings(steps: f64, range: f64) -> WalkStatistic {
+    if range <= 2. {
+        // The first step can't return to zero, and each later one does with probability 1/4,
+        // though never twice in a row
+        let pairs = (steps - 1.).max(0.);
+
+        return WalkStatistic {
+            value: 0.,
+            expected: pairs / 4.,
+            std_dev: if pairs > 0. {
+                (pairs + 2.).sqrt() / 4.
+            } else {
+                0.
+            },
+        };
+    }
+
+    let variance_per_step = (range - 1.) * (range - 2.) / (3. * range * range);
+    let time_scale = (1. - 1. / range) * (steps / variance_per_step).sqrt();
+
+    WalkStatistic {
+        value: 0.,
+        expected: time_scale * (2. / PI).sqrt(),
+        std_dev: time_scale * (1. - 2. / PI).sqrt(),
+    }
+}
+
+/// Mean and standard deviation of the longest monotone stretch over `steps` steps between points
+/// uniformly distributed over `0..range`.
+///
+/// `m` points in a row are strictly increasing with probability `C(range, m) / range^m`, so a
+/// stretch of at least `k` steps up starts at a given step with that probability for `m = k + 1`
+/// less that for `m = k + 2`. The number of such stretches in either direction is roughly Poisson
+/// distributed.
+fn expected_longest_stretch(steps: f64, range: f64) -> (f64, f64) {
+    let mut mean = 0.;
+    let mut second_moment = 0.;
+
+    // Probability of `k + 1` and `k + 2` points in a row increasing, starting from k = 1
+    let increasing = |m: usize, previous: f64| {
+        (previous * (range - (m - 1) as f64) / (m as f64 * range)).max(0.)
+    };
+    let mut at_least = increasing(2, 1.);
+    let mut next_at_least = increasing(3, at_least);
+
+    for k in 1..=steps as usize {
+        let count = 2. * (steps - k as f64 + 1.) * (at_least - next_at_least);
+        let at_least_k = 1. - (-count).exp();
+
+        if at_least_k < 1e-12 {
+            break;
+        }
+
+        mean += at_least_k;
+        second_moment += (2 * k - 1) as f64 * at_least_k;
+
+        at_least = next_at_least;
+        next_at_least = increasing(k + 3, next_at_least);
+    }
+
+    (mean, (second_moment - mean * mean).max(0.).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_stretch_is_limited_by_range() {
+        // Four increasing points out of 0..4 is always 0, 1, 2, 3, so nothing longer than three
+        // steps, and over this many steps there's almost certainly one of those
+        let (mean, std_dev) = expected_longest_stretch(10000., 4.);
+        assert!((mean - 3.).abs() < 1e-6, "{}", mean);
+        assert!(std_dev < 1e-3);
+
+        let (mean, _) = expected_longest_stretch(10000., 2.);
+        assert!((mean - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn longest_stretch_approaches_continuous_for_large_ranges() {
+        // With continuous values 1 / (k + 1)! of runs of k + 1 points are increasing
+        let factorial = |m: u64| (1..=m).product::<u64>() as f64;
+        let expected = (1..=15)
+            .map(|k| {
+                let count =
+                    2. * (1000. - k as f64 + 1.) * (1. / factorial(k + 1) - 1. / factorial(k + 2));
+                1. - (-count).exp()
+            })
+            .sum::<f64>();
+
+        let (continuous, _) = expected_longest_stretch(1000., 1e15);
+        assert!((continuous - expected).abs() < 1e-6, "{}", continuous);
+
+        let (discrete, _) = expected_longest_stretch(1000., 10.);
+        assert!(discrete < continuous);
+    }
+
+    #[test]
+    fn zero_crossings_are_exact_for_range_of_two() {
+        let expected = expected_zero_crossings(9999., 2.);
+        assert_eq!(expected.expected, 9998. / 4.);
+        assert_eq!(expected.std_dev, 10000f64.sqrt() / 4.);
+
+        // Returns to zero from 0 are each 1 followed by 0
+        let points = [0, 1, 0, 0, 1, 1, 0, 1];
+        assert_eq!(fitness(&points, 2).zero_crossings.value, 2.);
+    }
+}
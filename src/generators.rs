@@ -0,0 +1,606 @@
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::time::Instant;
+
+use anyhow::{anyhow, Error};
+use log::{debug, info};
+use rand::rngs::{SmallRng, StdRng};
+use rand::{thread_rng, RngCore, SeedableRng};
+use rand_chacha::{ChaCha12Rng, ChaCha20Rng, ChaCha8Rng};
+use rand_core::impls;
+use rand_isaac::{Isaac64Rng, IsaacRng};
+use rand_pcg::{Lcg128Xsl64, Lcg64Xsh32, Mcg128Xsl64};
+use rand_xorshift::XorShiftRng;
+use rand_xoshiro::{
+    SplitMix64, Xoroshiro128Plus, Xoroshiro128PlusPlus, Xoroshiro128StarStar, Xoroshiro64Star,
+    Xoroshiro64StarStar, Xoshiro128Plus, Xoshiro128PlusPlus, Xoshiro128StarStar, Xoshiro256Plus,
+    Xoshiro256PlusPlus, Xoshiro256StarStar, Xoshiro512Plus, Xoshiro512PlusPlus, Xoshiro512StarStar,
+};
+use serde::{Deserialize, Deserializer};
+
+use crate::samples;
+use crate::weak::{GlibcRand, MiddleSquare, Minstd, MsvcRand, Randu, Step, TruncatedLcg};
+
+/// A random number generator that can be analysed.
+pub trait Generator: Send + Sync {
+    /// Short name used on the command line and in output file names.
+    fn name(&self) -> &str;
+
+    /// One line description shown when listing generators.
+    fn description(&self) -> &str;
+
+    /// Whether the generator can be constructed from a fixed seed.
+    fn seedable(&self) -> bool;
+
+    /// Constructs a fresh instance of the generator from `seed`, or from entropy if there isn't
+    /// one. Generators that aren't seedable ignore `seed`.
+    fn rng(&self, seed: Option<u64>) -> Box<dyn RngCore>;
+
+    /// Number of bits at the top of each raw output from [`Generator::rng`] that the generator
+    /// actually produces. Any bits below them are always zero.
+    fn output_bits(&self) -> u32 {
+        u64::BITS
+    }
+
+    /// Draws `point_count` values in `0..range` from a fresh instance of the generator.
+    fn samples(&self, seed: Option<u64>, range: u64, point_count: u64) -> Vec<u64> {
+        samples::from_rng(&mut self.rng(seed), range, point_count)
+    }
+
+    /// Draws the same values as [`samples`] one at a time, without keeping them.
+    ///
+    /// [`samples`]: Generator::samples
+    fn stream(
+        &self,
+        seed: Option<u64>,
+        range: u64,
+        point_count: u64,
+    ) -> Box<dyn Iterator<Item = u64>> {
+        Box::new(samples::stream_from_rng(self.rng(seed), range, point_count))
+    }
+}
+
+/// A generator for any RNG implementing [`SeedableRng`].
+pub struct SeedableGenerator<R> {
+    name: &'static str,
+    description: &'static str,
+    output_bits: u32,
+    rng: PhantomData<fn() -> R>,
+}
+
+impl<R> SeedableGenerator<R> {
+    pub fn new(name: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            description,
+            output_bits: u64::BITS,
+            rng: PhantomData,
+        }
+    }
+
+    /// Marks the generator as only producing the top `bits` bits of each raw output.
+    pub fn with_output_bits(mut self, bits: u32) -> Self {
+        self.output_bits = bits;
+        self
+    }
+}
+
+impl<R: SeedableRng + RngCore + 'static> Generator for SeedableGenerator<R> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn seedable(&self) -> bool {
+        true
+    }
+
+    fn rng(&self, seed: Option<u64>) -> Box<dyn RngCore> {
+        match seed {
+            Some(seed) => Box::new(R::seed_from_u64(seed)),
+            None => Box::new(R::from_entropy()),
+        }
+    }
+
+    fn output_bits(&self) -> u32 {
+        self.output_bits
+    }
+}
+
+/// rand's thread local generator, which is always seeded from the OS.
+pub struct ThreadGenerator;
+
+impl Generator for ThreadGenerator {
+    fn name(&self) -> &str {
+        "standard"
+    }
+
+    fn description(&self) -> &str {
+        "rand's thread_rng (ChaCha)"
+    }
+
+    fn seedable(&self) -> bool {
+        false
+    }
+
+    fn rng(&self, _seed: Option<u64>) -> Box<dyn RngCore> {
+        Box::new(thread_rng())
+    }
+}
+
+/// Counts from 0 up to the range over and over.
+///
+/// The raw output from [`Generator::rng`] is a plain counter, so it is just as bad when looked at
+/// bit by bit.
+pub struct SequenceGenerator;
+
+impl Generator for SequenceGenerator {
+    fn name(&self) -> &str {
+        "sequence"
+    }
+
+    fn description(&self) -> &str {
+        "Counts from 0 up to the range, over and over"
+    }
+
+    fn seedable(&self) -> bool {
+        false
+    }
+
+    fn rng(&self, _seed: Option<u64>) -> Box<dyn RngCore> {
+        Box::new(CounterRng(0))
+    }
+
+    fn samples(&self, _seed: Option<u64>, range: u64, point_count: u64) -> Vec<u64> {
+        samples::sequence(range, point_count)
+    }
+
+    fn stream(
+        &self,
+        _seed: Option<u64>,
+        range: u64,
+        point_count: u64,
+    ) -> Box<dyn Iterator<Item = u64>> {
+        Box::new(samples::stream_sequence(range, point_count))
+    }
+}
+
+struct CounterRng(u64);
+
+impl RngCore for CounterRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let val = self.0;
+        self.0 = self.0.wrapping_add(1);
+        val
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// A generator name as given on the command line, optionally followed by `=<seed>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratorSpec {
+    pub name: String,
+    pub seed: Option<u64>,
+}
+
+impl FromStr for GeneratorSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, seed)) => Ok(Self {
+                name: name.to_string(),
+                seed: Some(
+                    seed.parse()
+                        .map_err(|_| anyhow!("Invalid seed '{}' for generator '{}'", seed, name))?,
+                ),
+            }),
+            None => Ok(Self {
+                name: s.to_string(),
+                seed: None,
+            }),
+        }
+    }
+}
+
+/// In a config file a spec can be written either as a string, like on the command line, or as a
+/// table with a `name` and an optional `seed`.
+impl<'de> Deserialize<'de> for GeneratorSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Table {
+            name: String,
+            seed: Option<u64>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Spec(String),
+            Table(Table),
+        }
+
+        match Repr::deserialize(deserializer).map_err(|_| {
+            serde::de::Error::custom(
+                "expected a generator like \"pcg32\" or \"pcg32=42\", or a table with a name and seed",
+            )
+        })? {
+            Repr::Spec(spec) => spec.parse().map_err(serde::de::Error::custom),
+            Repr::Table(Table { name, seed }) => Ok(Self { name, seed }),
+        }
+    }
+}
+
+impl Display for GeneratorSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.seed {
+            Some(seed) => write!(f, "{}={}", self.name, seed),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// A generator picked out of a [`Registry`], along with the seed it should be constructed from.
+#[derive(Clone, Copy)]
+pub struct Selected<'a> {
+    pub generator: &'a dyn Generator,
+    pub seed: Option<u64>,
+}
+
+impl Selected<'_> {
+    /// Constructs a fresh instance of the generator.
+    pub fn rng(&self) -> Box<dyn RngCore> {
+        debug!(
+            "Constructing {} {}",
+            self.generator.name(),
+            self.seed_description()
+        );
+
+        self.generator.rng(self.seed)
+    }
+
+    /// Draws `point_count` values in `0..range` from the generator.
+    pub fn samples(&self, range: u64, point_count: u64) -> Vec<u64> {
+        let start = Instant::now();
+        let points = self.generator.samples(self.seed, range, point_count);
+
+        info!(
+            "Drew {} samples in 0..{} from {} {} in {:.1?}",
+            points.len(),
+            range,
+            self.generator.name(),
+            self.seed_description(),
+            start.elapsed()
+        );
+
+        points
+    }
+
+    /// Draws the same values as [`samples`] one at a time, without keeping them.
+    ///
+    /// [`samples`]: Selected::samples
+    pub fn stream(&self, range: u64, point_count: u64) -> Box<dyn Iterator<Item = u64>> {
+        debug!(
+            "Streaming {} samples in 0..{} from {} {}",
+            point_count,
+            range,
+            self.generator.name(),
+            self.seed_description()
+        );
+
+        self.generator.stream(self.seed, range, point_count)
+    }
+
+    fn seed_description(&self) -> String {
+        match self.seed {
+            Some(seed) => format!("seeded with {}", seed),
+            None => "seeded from entropy".to_string(),
+        }
+    }
+
+    /// The generator's name, followed by its seed if it has one, like `pcg32_seed42`.
+    pub fn label(&self) -> String {
+        match self.seed {
+            Some(seed) => format!("{}_seed{}", self.generator.name(), seed),
+            None => self.generator.name().to_string(),
+        }
+    }
+
+    /// File name, without extension, used for output from a run over `range` and `point_count`.
+    pub fn file_stem(&self, range: u64, point_count: u64) -> String {
+        format!("{}_{}_{}_rng", self.label(), range, point_count)
+    }
+
+    /// Key/value pairs describing the generator and its seed, for embedding in output.
+    pub fn generator_metadata(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Generator", self.generator.name().to_string()),
+            (
+                "Seed",
+                self.seed
+                    .map_or_else(|| "entropy".to_string(), |seed| seed.to_string()),
+            ),
+        ]
+    }
+
+    /// Key/value pairs describing a run over `range` and `point_count`, for embedding in output.
+    pub fn metadata(&self, range: u64, point_count: u64) -> Vec<(&'static str, String)> {
+        let mut metadata = self.generator_metadata();
+        metadata.push(("Range", range.to_string()));
+        metadata.push(("Points", point_count.to_string()));
+        metadata
+    }
+}
+
+/// The set of generators available for analysis.
+pub struct Registry {
+    generators: Vec<Box<dyn Generator>>,
+}
+
+impl Registry {
+    /// Creates a registry with no generators in it.
+    pub fn empty() -> Self {
+        Self {
+            generators: Vec::new(),
+        }
+    }
+
+    /// Adds a generator to the registry.
+    pub fn register<G: Generator + 'static>(&mut self, generator: G) -> &mut Self {
+        self.generators.push(Box::new(generator));
+        self
+    }
+
+    /// Looks up a generator by name.
+    pub fn get(&self, name: &str) -> Option<&dyn Generator> {
+        self.iter().find(|generator| generator.name() == name)
+    }
+
+    /// Looks up each of `specs`, or returns every generator if `specs` is empty.
+    ///
+    /// Seedable generators without a seed of their own are given `default_seed`. Asking for a
+    /// specific seed on a generator that isn't seedable is an error.
+    pub fn select(
+        &self,
+        specs: &[GeneratorSpec],
+        default_seed: Option<u64>,
+    ) -> Result<Vec<Selected<'_>>, Error> {
+        let with_default_seed = |generator, seed: Option<u64>| Selected {
+            generator,
+            seed: seed.or(default_seed).filter(|_| generator.seedable()),
+        };
+
+        if specs.is_empty() {
+            return Ok(self
+                .iter()
+                .map(|generator| with_default_seed(generator, None))
+                .collect());
+        }
+
+        specs
+            .iter()
+            .map(|spec| {
+                let generator = self.get(&spec.name).ok_or_else(|| {
+                    anyhow!(
+                        "Unknown generator '{}', expected one of: {}",
+                        spec.name,
+                        self.iter()
+                            .map(|generator| generator.name())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })?;
+
+                if spec.seed.is_some() && !generator.seedable() {
+                    return Err(anyhow!("Generator '{}' can't be seeded", generator.name()));
+                }
+
+                Ok(with_default_seed(generator, spec.seed))
+            })
+            .collect()
+    }
+
+    /// Iterates over every registered generator, in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Generator> {
+        self.generators.iter().map(|generator| generator.as_ref())
+    }
+}
+
+impl Default for Registry {
+    /// Creates a registry containing every generator built in to this crate.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+
+        registry
+            .register(SequenceGenerator)
+            // Known weak generators, for calibration
+            .register(
+                SeedableGenerator::<Randu>::new("randu", "IBM's RANDU")
+                    .with_output_bits(Randu::BITS),
+            )
+            .register(
+                SeedableGenerator::<GlibcRand>::new("glibcrand", "The LCG behind glibc's rand()")
+                    .with_output_bits(GlibcRand::BITS),
+            )
+            .register(
+                SeedableGenerator::<MsvcRand>::new("msvcrand", "The LCG behind MSVC's rand()")
+                    .with_output_bits(MsvcRand::BITS),
+            )
+            .register(
+                SeedableGenerator::<MiddleSquare>::new(
+                    "middlesquare",
+                    "Von Neumann's middle-square method",
+                )
+                .with_output_bits(MiddleSquare::BITS),
+            )
+            .register(
+                SeedableGenerator::<Minstd>::new(
+                    "minstd",
+                    "Park and Miller's MINSTD Lehmer generator",
+                )
+                .with_output_bits(Minstd::BITS),
+            )
+            .register(
+                SeedableGenerator::<TruncatedLcg>::new(
+                    "truncatedlcg",
+                    "64 bit LCG truncated to its low 32 bits",
+                )
+                .with_output_bits(TruncatedLcg::BITS),
+            )
+            // Generators from rand and friends
+            .register(ThreadGenerator)
+            .register(SeedableGenerator::<StdRng>::new(
+                "stdrng",
+                "rand's StdRng, currently ChaCha12",
+            ))
+            .register(SeedableGenerator::<SmallRng>::new(
+                "smallrng",
+                "rand's SmallRng, currently Xoshiro256++",
+            ))
+            .register(SeedableGenerator::<XorShiftRng>::new(
+                "xorshift", "XorShift",
+            ))
+            // xoshiro and xoroshiro
+            .register(SeedableGenerator::<Xoshiro128Plus>::new(
+                "xoshiro128plus",
+                "Xoshiro128+",
+            ))
+            .register(SeedableGenerator::<Xoshiro128PlusPlus>::new(
+                "xoshiro128plusplus",
+                "Xoshiro128++",
+            ))
+            .register(SeedableGenerator::<Xoshiro128StarStar>::new(
+                "xoshiro128starstar",
+                "Xoshiro128**",
+            ))
+            .register(SeedableGenerator::<Xoshiro256Plus>::new(
+                "xoshiro256plus",
+                "Xoshiro256+",
+            ))
+            .register(SeedableGenerator::<Xoshiro256PlusPlus>::new(
+                "xoshiro256plusplus",
+                "Xoshiro256++",
+            ))
+            .register(SeedableGenerator::<Xoshiro256StarStar>::new(
+                "xoshiro256starstar",
+                "Xoshiro256**",
+            ))
+            .register(SeedableGenerator::<Xoshiro512Plus>::new(
+                "xoshiro512plus",
+                "Xoshiro512+",
+            ))
+            .register(SeedableGenerator::<Xoshiro512PlusPlus>::new(
+                "xoshiro512plusplus",
+                "Xoshiro512++",
+            ))
+            .register(SeedableGenerator::<Xoshiro512StarStar>::new(
+                "xoshiro512starstar",
+                "Xoshiro512**",
+            ))
+            .register(SeedableGenerator::<Xoroshiro64Star>::new(
+                "xoroshiro64star",
+                "Xoroshiro64*",
+            ))
+            .register(SeedableGenerator::<Xoroshiro64StarStar>::new(
+                "xoroshiro64starstar",
+                "Xoroshiro64**",
+            ))
+            .register(SeedableGenerator::<Xoroshiro128Plus>::new(
+                "xoroshiro128plus",
+                "Xoroshiro128+",
+            ))
+            .register(SeedableGenerator::<Xoroshiro128PlusPlus>::new(
+                "xoroshiro128plusplus",
+                "Xoroshiro128++",
+            ))
+            .register(SeedableGenerator::<Xoroshiro128StarStar>::new(
+                "xoroshiro128starstar",
+                "Xoroshiro128**",
+            ))
+            .register(SeedableGenerator::<SplitMix64>::new(
+                "splitmix64",
+                "SplitMix64",
+            ))
+            // PCG
+            .register(SeedableGenerator::<Lcg64Xsh32>::new(
+                "pcg32",
+                "Pcg32 (Lcg64Xsh32)",
+            ))
+            .register(SeedableGenerator::<Lcg128Xsl64>::new(
+                "pcg64",
+                "Pcg64 (Lcg128Xsl64)",
+            ))
+            .register(SeedableGenerator::<Mcg128Xsl64>::new(
+                "pcg64mcg",
+                "Pcg64Mcg (Mcg128Xsl64)",
+            ))
+            // ChaCha
+            .register(SeedableGenerator::<ChaCha8Rng>::new(
+                "chacha8",
+                "ChaCha with 8 rounds",
+            ))
+            .register(SeedableGenerator::<ChaCha12Rng>::new(
+                "chacha12",
+                "ChaCha with 12 rounds",
+            ))
+            .register(SeedableGenerator::<ChaCha20Rng>::new(
+                "chacha20",
+                "ChaCha with 20 rounds",
+            ))
+            // ISAAC
+            .register(SeedableGenerator::<IsaacRng>::new("isaac", "ISAAC"))
+            .register(SeedableGenerator::<Isaac64Rng>::new("isaac64", "ISAAC-64"));
+
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_below_the_output_bits_are_zero() {
+        let registry = Registry::default();
+
+        for generator in registry.iter() {
+            let padding = u64::MAX.checked_shr(generator.output_bits()).unwrap_or(0);
+            let mut rng = generator.rng(Some(1));
+
+            for _ in 0..100 {
+                assert_eq!(rng.next_u64() & padding, 0, "{}", generator.name());
+            }
+        }
+
+        assert_eq!(registry.get("msvcrand").unwrap().output_bits(), 15);
+        assert_eq!(registry.get("pcg32").unwrap().output_bits(), 64);
+    }
+
+    #[test]
+    fn sequence_stops_partway_through_a_pass() {
+        let sequence = SequenceGenerator;
+
+        assert_eq!(sequence.samples(None, 3, 7), [0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(
+            sequence.stream(None, 3, 7).collect::<Vec<_>>(),
+            [0, 1, 2, 0, 1, 2, 0]
+        );
+    }
+}
@@ -0,0 +1,18 @@
+//! Tools for judging how well distributed a random number generator's output is.
+//!
+//! Generators are looked up in a [`generators::Registry`], their samples turned into a fitness walk
+//! with [`fitness`] and drawn to disk with [`plot`].
+
+pub mod analysis;
+pub mod error;
+pub mod experiment;
+pub mod export;
+pub mod fitness;
+pub mod generators;
+pub mod logging;
+pub mod output;
+pub mod plot;
+pub mod samples;
+pub mod stream;
+pub mod sweep;
+pub mod weak;
@@ -0,0 +1,51 @@
+//! Sets up log4rs, either from a config file or from a verbosity level.
+
+use std::path::Path;
+
+use anyhow::{Context, Error};
+use log::LevelFilter;
+use log4rs::append::console::{ConsoleAppender, Target};
+use log4rs::config::{Appender, Config, Logger, Root};
+use log4rs::encode::pattern::PatternEncoder;
+
+/// Pattern for log lines written to stderr when there's no config file.
+const PATTERN: &str = "{d(%H:%M:%S%.3f)} {h({l:<5})} {t} - {m}{n}";
+
+/// Starts logging using the log4rs config in `config_file` if there is one, otherwise to stderr at a
+/// level picked by `verbosity`: warnings and errors only at 0, then info, debug and trace.
+///
+/// Other crates only log errors unless `verbosity` is 3 or more, as some of them warn about every
+/// chart they draw, like usvg about missing fonts.
+pub fn init(verbosity: u8, config_file: Option<&Path>) -> Result<(), Error> {
+    if let Some(config_file) = config_file {
+        return log4rs::init_file(config_file, Default::default())
+            .with_context(|| format!("Couldn't load log config {}", config_file.display()));
+    }
+
+    let level = match verbosity {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    let stderr = ConsoleAppender::builder()
+        .target(Target::Stderr)
+        .encoder(Box::new(PatternEncoder::new(PATTERN)))
+        .build();
+
+    let dependency_level = if verbosity >= 3 {
+        LevelFilter::Trace
+    } else {
+        LevelFilter::Error
+    };
+
+    let config = Config::builder()
+        .appender(Appender::builder().build("stderr", Box::new(stderr)))
+        .logger(Logger::builder().build(env!("CARGO_CRATE_NAME"), level))
+        .build(Root::builder().appender("stderr").build(dependency_level))?;
+
+    log4rs::init_config(config)?;
+
+    Ok(())
+}
@@ -1,160 +1,1059 @@
-use std::io::Write;
+use std::fmt::Write as _;
 use std::path::PathBuf;
 
-use anyhow::{anyhow, Error};
-use charts_rs::svg_to_png;
-use directories::BaseDirs;
-use itertools::Itertools;
-use plotlib::page::Page;
-use plotlib::repr::Plot;
-use plotlib::style::{PointMarker, PointStyle};
-use plotlib::view::ContinuousView;
-use rand::{thread_rng, Rng};
-use rand_xorshift::XorShiftRng;
-use rand_xoshiro::{rand_core::SeedableRng, Xoshiro256PlusPlus};
-
-fn plot(full_path: &PathBuf, points: &Vec<u64>) -> Result<(), Error> {
-    let max_y = points.iter().max().unwrap();
-    let mut current_fitness: i64 = *max_y as i64 / 2;
-
-    // Start with turning our random sequence into a vec of tuples of x, y
-    let time_series: Plot = Plot::new(
-        points
-            .iter()
-            .enumerate()
-            .map(|x| (x.0 as f64, *x.1 as f64))
-            .collect::<Vec<_>>(),
-    )
-    .point_style(
-        PointStyle::new()
-            .marker(PointMarker::Square) // setting the marker to be a square
-            .colour("#DD3355") // and a custom colour
-            .size(3.),
+use anyhow::{anyhow, Context, Error};
+use clap::{Args, Parser, Subcommand};
+use log::info;
+use rayon::prelude::*;
+
+use procedural_fitness::analysis::bits::{bit_bias, BitStats};
+use procedural_fitness::analysis::correlation::autocorrelation;
+use procedural_fitness::analysis::{self, AnalysisOptions, Test, TestResult};
+use procedural_fitness::error::FitnessError;
+use procedural_fitness::experiment::{AnalysisConfig, Experiment, OutputConfig, Run};
+use procedural_fitness::export::{ExportFormat, RunData};
+use procedural_fitness::fitness::{fitness, Fitness};
+use procedural_fitness::generators::{GeneratorSpec, Registry};
+use procedural_fitness::logging;
+use procedural_fitness::output::{create_run_dir, default_output_dir};
+use procedural_fitness::plot::bits::plot_bit_heatmap;
+use procedural_fitness::plot::correlogram::plot_correlogram;
+use procedural_fitness::plot::grid::Grid;
+use procedural_fitness::plot::histogram::{plot_bins, plot_histogram};
+use procedural_fitness::plot::lag::{plot_lag_2d, plot_lag_3d, LagView};
+use procedural_fitness::plot::noise::{plot_noise, NoiseMode};
+use procedural_fitness::plot::style::{Downsample, Marker, Style, StyleOverrides, Theme};
+use procedural_fitness::plot::{plot, plot_series, Chart, OutputFormat, RenderOptions};
+use procedural_fitness::stream::{self, StreamSummary, DEFAULT_SERIES_BUCKETS};
+use procedural_fitness::sweep::{write_summary_csv, SummaryRow, Sweep};
+
+/// Command line interface for generating fitness plots.
+#[derive(Parser)]
+#[command(author, version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Log more detail to stderr, up to -vvv
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// log4rs config file to use instead of logging to stderr
+    #[arg(long, global = true)]
+    log_config: Option<PathBuf>,
+
+    /// Number of generators and charts to work on at once [default: one per CPU]
+    #[arg(short, long, global = true)]
+    jobs: Option<usize>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Plot the output of one or more generators
+    Plot(Box<PlotArgs>),
+    /// Run statistical tests on the output of one or more generators
+    Test(TestArgs),
+    /// Run every generator over every range and point count, drawing a grid of charts for each
+    /// and a table of how the tests fare
+    Sweep(Box<SweepArgs>),
+    /// Analyse runs too long to keep in memory one sample at a time, drawing the time series and
+    /// histogram from what's kept
+    Stream(Box<StreamArgs>),
+    /// List the generators that can be plotted
+    ListGenerators,
+}
+
+#[derive(Args)]
+struct SampleArgs {
+    /// TOML or YAML experiment file, which any of the other options override
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Numbers of points to sample from each generator, separated by commas. Each can also be a
+    /// geometric progression like 1000..1000000*10 [default: 10000]
+    #[arg(short = 'n', long, value_delimiter = ',')]
+    points: Vec<Sweep>,
+
+    /// Upper bounds (exclusive) of the sampled values, separated by commas. Each can also be a
+    /// geometric progression like 100..1000000*10 [default: 10000]
+    #[arg(short, long = "range", value_delimiter = ',')]
+    ranges: Vec<Sweep>,
+
+    /// Generators to use, defaults to all of them. Append `=<seed>` to seed a single generator
+    #[arg(short, long = "generator")]
+    generators: Vec<GeneratorSpec>,
+
+    /// Seed for every seedable generator that isn't given its own, defaults to entropy
+    #[arg(short, long)]
+    seed: Option<u64>,
+}
+
+impl SampleArgs {
+    /// The experiment given on the command line, on top of the experiment file if there is one.
+    /// Everything else in `experiment` is kept as long as the file doesn't set it.
+    fn experiment(&self, registry: &Registry, experiment: Experiment) -> Result<Experiment, Error> {
+        let experiment = Experiment {
+            generators: self.generators.clone(),
+            seed: self.seed,
+            ranges: self.ranges.clone(),
+            points: self.points.clone(),
+            ..experiment
+        };
+
+        let experiment = match &self.config {
+            Some(path) => experiment.or(Experiment::from_file(path)?),
+            None => experiment,
+        };
+
+        experiment.validate(registry)?;
+        Ok(experiment)
+    }
+}
+
+#[derive(Args)]
+struct AnalysisArgs {
+    /// Number of bins to use for binned tests and the histogram [default: 100]
+    #[arg(long)]
+    bins: Option<usize>,
+
+    /// Tests with a p-value below this, or for most tests above one minus this, fail [default: 0.01]
+    #[arg(long)]
+    significance: Option<f64>,
+
+    /// Longest lag to check for autocorrelation [default: 20]
+    #[arg(long)]
+    max_lag: Option<usize>,
+
+    /// Tests to run, separated by commas: chi-squared, kolmogorov-smirnov, anderson-darling,
+    /// ljung-box, runs or bit-bias [default: all of them]
+    #[arg(long, value_delimiter = ',')]
+    tests: Vec<Test>,
+}
+
+impl AnalysisArgs {
+    fn config(&self) -> AnalysisConfig {
+        AnalysisConfig {
+            bins: self.bins,
+            significance: self.significance,
+            max_lag: self.max_lag,
+            tests: self.tests.clone(),
+        }
+    }
+}
+
+#[derive(Args)]
+struct PlotArgs {
+    #[command(flatten)]
+    sample: SampleArgs,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    #[command(flatten)]
+    chart: ChartArgs,
+
+    /// Degrees to rotate the 3D lag plot about its vertical axis
+    #[arg(long, default_value_t = LagView::default().azimuth, allow_negative_numbers = true)]
+    azimuth: f64,
+
+    /// Degrees to tilt the 3D lag plot towards the viewer
+    #[arg(long, default_value_t = LagView::default().elevation, allow_negative_numbers = true)]
+    elevation: f64,
+
+    /// Whether the noise image shows one bit or one byte of raw output per pixel
+    #[arg(long, default_value = "bits")]
+    noise_mode: NoiseMode,
+
+    /// Width of the noise image in pixels
+    #[arg(long, default_value_t = 512)]
+    noise_width: u32,
+
+    /// Height of the noise image in pixels
+    #[arg(long, default_value_t = 512)]
+    noise_height: u32,
+
+    /// Number of blocks of raw output to split the bit bias heatmap into
+    #[arg(long, default_value_t = 32)]
+    bit_blocks: u64,
+
+    /// Also write the samples, fitness walk and test statistics to files, as csv, json or csv,json
+    #[arg(long, value_delimiter = ',')]
+    export: Vec<ExportFormat>,
+}
+
+/// Where charts are written, and how they look.
+#[derive(Args)]
+struct ChartArgs {
+    /// Directory to write charts into, defaults to a directory under the user's data directory.
+    /// Each run gets its own directory inside this one
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+
+    /// Name of this run's directory, defaults to the current time
+    #[arg(long)]
+    run_id: Option<String>,
+
+    #[command(flatten)]
+    style: StyleArgs,
+
+    /// Whether to write charts as png, svg or both. Noise images and heatmaps are always png
+    /// [default: png]
+    #[arg(long)]
+    format: Option<OutputFormat>,
+
+    /// Charts to draw for each run, separated by commas: series, correlogram, histogram, lag2d,
+    /// lag3d, noise or bits. Sweeps and streams only draw the series and histogram
+    /// [default: all of them]
+    #[arg(long, value_delimiter = ',')]
+    charts: Vec<Chart>,
+}
+
+#[derive(Args)]
+struct StyleArgs {
+    /// TOML file setting any of the options below, which take precedence over it
+    #[arg(long = "style")]
+    style_file: Option<PathBuf>,
+
+    /// Default colours to start from, light or dark
+    #[arg(long)]
+    theme: Option<Theme>,
+
+    /// Width of the output image in pixels [default: 1920]
+    #[arg(long)]
+    width: Option<u32>,
+
+    /// Height of the output image in pixels [default: 1080]
+    #[arg(long)]
+    height: Option<u32>,
+
+    /// Colour to fill the background of each chart with, transparent by default
+    #[arg(long)]
+    background: Option<String>,
+
+    /// Colour of the axes and labels
+    #[arg(long)]
+    foreground: Option<String>,
+
+    /// Colour of the sampled points
+    #[arg(long)]
+    series_colour: Option<String>,
+
+    /// Colour of the fitness walk
+    #[arg(long)]
+    walk_colour: Option<String>,
+
+    /// Colour of the expected band on histograms
+    #[arg(long)]
+    band_colour: Option<String>,
+
+    /// Shape of each sampled point, square, circle or cross
+    #[arg(long)]
+    marker: Option<Marker>,
+
+    /// Size of each sampled point
+    #[arg(long)]
+    marker_size: Option<f32>,
+
+    /// Shape of each step of the fitness walk
+    #[arg(long)]
+    walk_marker: Option<Marker>,
+
+    /// Size of each step of the fitness walk
+    #[arg(long)]
+    walk_size: Option<f32>,
+
+    /// Whether to draw the fitness walk over the sampled points
+    #[arg(long)]
+    show_walk: Option<bool>,
+
+    /// How to thin out time series with too many points to draw, none, minmax or lttb
+    /// [default: minmax]
+    #[arg(long)]
+    downsample: Option<Downsample>,
+
+    /// Most points of a time series or fitness walk to draw before downsampling [default: 4000]
+    #[arg(long)]
+    downsample_threshold: Option<usize>,
+
+    /// Whether to shade downsampled time series by how many points land in each part of them
+    /// [default: true]
+    #[arg(long)]
+    density: Option<bool>,
+}
+
+impl StyleArgs {
+    /// The style given on the command line, on top of the style file if there is one.
+    fn overrides(&self) -> Result<StyleOverrides, Error> {
+        let overrides = StyleOverrides {
+            theme: self.theme,
+            width: self.width,
+            height: self.height,
+            background: self.background.clone(),
+            foreground: self.foreground.clone(),
+            series_colour: self.series_colour.clone(),
+            walk_colour: self.walk_colour.clone(),
+            band_colour: self.band_colour.clone(),
+            marker: self.marker,
+            marker_size: self.marker_size,
+            walk_marker: self.walk_marker,
+            walk_size: self.walk_size,
+            show_walk: self.show_walk,
+            downsample: self.downsample,
+            downsample_threshold: self.downsample_threshold,
+            density: self.density,
+        };
+
+        match &self.style_file {
+            Some(path) => Ok(overrides.or(StyleOverrides::from_file(path)?)),
+            None => Ok(overrides),
+        }
+    }
+}
+
+#[derive(Args)]
+struct SweepArgs {
+    #[command(flatten)]
+    sample: SampleArgs,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    #[command(flatten)]
+    chart: ChartArgs,
+}
+
+#[derive(Args)]
+struct StreamArgs {
+    #[command(flatten)]
+    sample: SampleArgs,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    #[command(flatten)]
+    chart: ChartArgs,
+
+    /// Fewest buckets to downsample the time series into for drawing. There are at most twice as
+    /// many
+    #[arg(long, default_value_t = DEFAULT_SERIES_BUCKETS)]
+    series_buckets: usize,
+}
+
+#[derive(Args)]
+struct TestArgs {
+    #[command(flatten)]
+    sample: SampleArgs,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+}
+
+/// Runs the statistical tests on `points`, followed by the bit bias test on the run's raw output
+/// if it's one of the tests to run.
+fn run_tests(
+    run: &Run,
+    points: &[u64],
+    options: &AnalysisOptions,
+) -> Result<(Vec<TestResult>, Option<BitStats>), Error> {
+    let mut results = analysis::run(points, run.range, options)?;
+
+    if !options.runs(Test::BitBias) {
+        return Ok((results, None));
+    }
+
+    let bit_stats = BitStats::from_rng(
+        run.selected.rng().as_mut(),
+        run.point_count,
+        run.selected.generator.output_bits(),
     );
+    results.push(bit_bias(&bit_stats)?);
 
-    let mut last_val = points[0] as i64;
+    Ok((results, Some(bit_stats)))
+}
 
-    // Now plot the fitness indicator
-    let fitness_indicator: Plot = Plot::new(
-        points
-            .iter()
-            .map(|val| {
-                let val = *val as i64;
-                if val != last_val {
-                    current_fitness += (val - last_val) / (val - last_val).abs()
-                };
-                last_val = val;
-                current_fitness
-            })
-            .collect::<Vec<_>>()
-            .iter()
-            .enumerate()
-            .map(|x| (x.0 as f64, *x.1 as f64))
-            .collect::<Vec<_>>(),
-    )
-    .point_style(
-        PointStyle::new() // uses the default marker
-            .colour("#35C788")
-            .size(4.),
-    ); // and a different colour
-
-    // The 'view' describes what set of data is drawn
-    let v = ContinuousView::new()
-        .add(time_series)
-        .add(fitness_indicator)
-        .x_range(0., points.len() as f64)
-        .y_range(0., *max_y as f64)
-        .x_label("Time")
-        .y_label("Value");
-
-    // A page with a single view is then saved to an PNG file
-    let png_path = full_path.with_extension("png");
-
-    let mut file = std::fs::File::create(png_path)?;
-    file.write_all(
-        &svg_to_png(
-            &Page::single(&v)
-                .dimensions(1920, 1080)
-                .to_svg()
-                .unwrap()
-                .to_string(),
-        )
-        .unwrap(),
+/// Runs the statistical tests on `points` and describes them with a line per test, followed by the
+/// fitness walk statistics. Returns the description, the test results and the fitness walk.
+fn report_tests(
+    run: &Run,
+    points: &[u64],
+    options: &AnalysisOptions,
+) -> Result<(String, Vec<TestResult>, Fitness), Error> {
+    let mut report = String::new();
+    writeln!(report, "{}", run.file_stem())?;
+
+    let (results, bit_stats) = run_tests(run, points, options)?;
+
+    for result in &results {
+        let passed = result.passed(options.significance);
+
+        writeln!(
+            report,
+            "  {}  {}",
+            result,
+            if passed { "pass" } else { "FAIL" }
+        )?;
+    }
+
+    let acf = autocorrelation(points, options.max_lag);
+
+    if let Some((lag, r)) = acf
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+    {
+        writeln!(
+            report,
+            "  {:<20} lag 1 {:>8.4}  largest {:>8.4} at lag {}",
+            "autocorrelation",
+            acf[0],
+            r,
+            lag + 1
+        )?;
+    }
+
+    if let Some(bit_stats) = bit_stats {
+        let bit_bias_of =
+            |bit: usize| bit_stats.ones_z_score(bit).powi(2) + bit_stats.flips_z_score(bit).powi(2);
+
+        if let Some(bit) = bit_stats
+            .counted()
+            .max_by(|a, b| bit_bias_of(*a).total_cmp(&bit_bias_of(*b)))
+        {
+            writeln!(
+                report,
+                "  {:<20} bit {:>2} set {:.4} of the time, changes {:.4} of the time",
+                "most biased bit",
+                bit,
+                bit_stats.ones_frequency(bit),
+                bit_stats.flip_probability(bit)
+            )?;
+        }
+    }
+
+    let fitness = fitness(points, run.range);
+    write_fitness(&mut report, &fitness)?;
+
+    Ok((report, results, fitness))
+}
+
+/// Describes the fitness walk statistics with a line each, followed by the score.
+fn write_fitness(report: &mut String, fitness: &Fitness) -> Result<(), Error> {
+    writeln!(
+        report,
+        "  {:<20} {}",
+        "max excursion", fitness.max_excursion
+    )?;
+    writeln!(
+        report,
+        "  {:<20} {}",
+        "final displacement", fitness.final_displacement
+    )?;
+    writeln!(
+        report,
+        "  {:<20} {}",
+        "zero crossings", fitness.zero_crossings
     )?;
+    writeln!(
+        report,
+        "  {:<20} {}",
+        "longest stretch", fitness.longest_monotone_stretch
+    )?;
+    writeln!(report, "  {:<20} {:.3}", "fitness score", fitness.score())?;
 
     Ok(())
 }
 
-fn sequence_rng_plot(base_path: &PathBuf, range: u64, point_count: u64) -> Result<(), Error> {
-    let base_vec = (0..range).collect_vec();
+/// Describes what was kept from streaming a run's samples: the chi-squared test if it's one of the
+/// tests to run, the mean and variance against those of a uniform distribution, and the fitness
+/// walk statistics.
+fn report_stream(
+    run: &Run,
+    summary: &StreamSummary,
+    options: &AnalysisOptions,
+) -> Result<String, Error> {
+    let mut report = String::new();
+    writeln!(report, "{}", run.file_stem())?;
+
+    if options.runs(Test::ChiSquared) {
+        let passed = summary.chi_squared.passed(options.significance);
+        writeln!(
+            report,
+            "  {}  {}",
+            summary.chi_squared,
+            if passed { "pass" } else { "FAIL" }
+        )?;
+    }
+
+    let range = run.range as f64;
+    let moments = &summary.moments;
+    let expected_mean = (range - 1.) / 2.;
+    let expected_variance = (range * range - 1.) / 12.;
+    let standard_error = (expected_variance / moments.count() as f64).sqrt();
+
+    writeln!(
+        report,
+        "  {:<20} {:>14.4}  expected {:>14.4}, z {:>7.2}",
+        "mean",
+        moments.mean(),
+        expected_mean,
+        if standard_error > 0. {
+            (moments.mean() - expected_mean) / standard_error
+        } else {
+            0.
+        }
+    )?;
+    writeln!(
+        report,
+        "  {:<20} {:>14.4}  expected {:>14.4}",
+        "variance",
+        moments.variance(),
+        expected_variance
+    )?;
+
+    write_fitness(&mut report, &summary.fitness)?;
+
+    Ok(report)
+}
+
+/// What's printed about a single run once it's finished.
+struct Report {
+    /// The run's lines of test results and fitness statistics.
+    text: String,
+
+    /// The run's file stem, which it's ranked under.
+    stem: String,
+
+    score: f64,
+}
+
+/// Works on every one of `runs` at once, as many at a time as there are jobs, passing each its
+/// file stem.
+///
+/// The results are kept in order, so the output is the same however many jobs there are, and one
+/// run failing doesn't stop the rest.
+fn each_run<T: Send>(
+    runs: &[Run],
+    work: impl Fn(&Run, String) -> Result<T, Error> + Sync,
+) -> Vec<Result<T, Error>> {
+    runs.par_iter()
+        .enumerate()
+        .map(|(i, run)| {
+            let stem = run.file_stem();
+            info!("Starting {} ({} of {})", stem, i + 1, runs.len());
+
+            work(run, stem.clone()).with_context(|| format!("{} failed", stem))
+        })
+        .collect()
+}
 
-    let mut point_vec: Vec<u64> = Vec::new();
+/// Splits `results` into what succeeded and the errors from what didn't.
+fn partition<T>(results: impl IntoIterator<Item = Result<T, Error>>) -> (Vec<T>, Vec<Error>) {
+    let mut succeeded = Vec::new();
+    let mut failed = Vec::new();
 
-    while point_vec.len() < point_count as usize {
-        point_vec.extend(base_vec.iter());
+    for result in results {
+        match result {
+            Ok(value) => succeeded.push(value),
+            Err(e) => failed.push(e),
+        }
     }
 
-    let mut final_path = base_path.clone();
-    final_path.push(format!("sequence_{}_{}_rng", range, point_count));
+    (succeeded, failed)
+}
 
-    plot(&final_path, &point_vec)
+/// Prints each of `failures` to stderr, with its causes.
+fn print_failures(failures: &[Error]) {
+    for failure in failures {
+        eprintln!("Error: {:#}", failure);
+    }
 }
 
-fn standard_rng_plot(base_path: &PathBuf, range: u64, point_count: u64) -> Result<(), Error> {
-    let mut rng = thread_rng();
+/// Prints the report of each run that succeeded in order, followed by their fitness scores, best
+/// first, then what went wrong with the rest.
+fn print_reports(reports: Vec<Result<Report, Error>>) -> Result<(), Error> {
+    let total = reports.len();
+    let (reports, failures) = partition(reports);
+    let mut scores = Vec::new();
 
-    let point_vec = (0..point_count)
-        .map(|_| rng.gen_range(0..range))
-        .collect_vec();
+    for report in reports {
+        print!("{}", report.text);
+        scores.push((report.stem, report.score));
+    }
 
-    let mut final_path = base_path.clone();
-    final_path.push(format!("standard_{}_{}_rng", range, point_count));
+    scores.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    println!("Ranking by fitness score, lower is better");
+
+    for (rank, (name, score)) in scores.iter().enumerate() {
+        println!("  {:>3}. {:<50} {:.3}", rank + 1, name, score);
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        print_failures(&failures);
+        Err(anyhow!("{} of {} runs failed", failures.len(), total))
+    }
+}
 
-    plot(&final_path, &point_vec)
+/// Where a command that draws charts writes them, and how they look.
+struct ChartOutput {
+    /// This run's own directory.
+    base_path: PathBuf,
+    style: Style,
+    format: OutputFormat,
 }
 
-fn xorshift_rng_plot(base_path: &PathBuf, range: u64, point_count: u64) -> Result<(), Error> {
-    let mut rng = XorShiftRng::from_entropy();
+impl ChartOutput {
+    /// Creates the directory for `experiment`'s output and prints where it is.
+    fn create(experiment: &Experiment) -> Result<Self, Error> {
+        let output_dir = match &experiment.output.dir {
+            Some(output_dir) => output_dir.clone(),
+            None => default_output_dir()?,
+        };
+        let style = experiment.chart_style()?;
+        let base_path = create_run_dir(&output_dir, experiment.output.run_id.as_deref())?;
 
-    let point_vec = (0..point_count)
-        .map(|_| rng.gen_range(0..range))
-        .collect_vec();
+        println!("Writing output to {}", base_path.display());
 
-    let mut final_path = base_path.clone();
-    final_path.push(format!("xorshift_{}_{}_rng", range, point_count));
+        Ok(Self {
+            base_path,
+            style,
+            format: experiment.output.format.unwrap_or(OutputFormat::Png),
+        })
+    }
 
-    plot(&final_path, &point_vec)
+    /// Options for drawing a chart with `metadata`.
+    fn render_options(&self, metadata: Vec<(&'static str, String)>) -> RenderOptions {
+        RenderOptions {
+            style: self.style.clone(),
+            format: self.format,
+            metadata,
+        }
+    }
 }
 
-fn xoshiro256plusplus_rng_plot(
-    base_path: &PathBuf,
-    range: u64,
-    point_count: u64,
-) -> Result<(), Error> {
-    let mut rng = Xoshiro256PlusPlus::from_entropy();
+/// The experiment for a command that draws charts, from the command line on top of any experiment
+/// file, along with where its output goes, which is created.
+fn chart_experiment(
+    registry: &Registry,
+    sample: &SampleArgs,
+    analysis: &AnalysisArgs,
+    chart: &ChartArgs,
+    export: Vec<ExportFormat>,
+) -> Result<(Experiment, ChartOutput), Error> {
+    let experiment = sample.experiment(
+        registry,
+        Experiment {
+            analysis: analysis.config(),
+            style: chart.style.overrides()?,
+            output: OutputConfig {
+                dir: chart.output_dir.clone(),
+                run_id: chart.run_id.clone(),
+                format: chart.format,
+                export,
+                charts: chart.charts.clone(),
+            },
+            ..Default::default()
+        },
+    )?;
+
+    let output = ChartOutput::create(&experiment)?;
+
+    Ok((experiment, output))
+}
+
+fn run_plot(registry: &Registry, args: &PlotArgs) -> Result<(), Error> {
+    let (experiment, output) = chart_experiment(
+        registry,
+        &args.sample,
+        &args.analysis,
+        &args.chart,
+        args.export.clone(),
+    )?;
+
+    let analysis_options = experiment.analysis.options();
+    let base_path = &output.base_path;
+    let runs = experiment.runs(registry)?;
+
+    let reports = each_run(&runs, |run, stem| {
+        let points = run.samples();
+        let options = output.render_options(run.metadata());
+        let lag_view = LagView {
+            azimuth: args.azimuth,
+            elevation: args.elevation,
+        };
+
+        type Draw<'a> = &'a (dyn Fn() -> Result<(), FitnessError> + Sync);
+
+        let charts: [(Chart, Draw); 7] = [
+            (Chart::Series, &|| {
+                plot(&base_path.join(&stem), &points, &options)
+            }),
+            (Chart::Correlogram, &|| {
+                plot_correlogram(
+                    &base_path.join(format!("{}_correlogram", stem)),
+                    &autocorrelation(&points, analysis_options.max_lag),
+                    points.len(),
+                    &options,
+                )
+            }),
+            (Chart::Histogram, &|| {
+                plot_histogram(
+                    &base_path.join(format!("{}_histogram", stem)),
+                    &points,
+                    run.range,
+                    analysis_options.bins,
+                    &options,
+                )
+            }),
+            (Chart::Lag2d, &|| {
+                plot_lag_2d(
+                    &base_path.join(format!("{}_lag2d", stem)),
+                    &points,
+                    run.range,
+                    &options,
+                )
+            }),
+            (Chart::Lag3d, &|| {
+                plot_lag_3d(
+                    &base_path.join(format!("{}_lag3d", stem)),
+                    &points,
+                    run.range,
+                    lag_view,
+                    &options,
+                )
+            }),
+            (Chart::Noise, &|| {
+                plot_noise(
+                    &base_path.join(format!("{}_noise", stem)),
+                    run.selected.rng().as_mut(),
+                    args.noise_mode,
+                    args.noise_width,
+                    args.noise_height,
+                    &options.metadata,
+                )
+            }),
+            (Chart::Bits, &|| {
+                plot_bit_heatmap(
+                    &base_path.join(format!("{}_bits", stem)),
+                    &BitStats::blocks_from_rng(
+                        run.selected.rng().as_mut(),
+                        run.point_count,
+                        args.bit_blocks,
+                        run.selected.generator.output_bits(),
+                    ),
+                    &options,
+                )
+            }),
+        ];
 
-    let point_vec = (0..point_count)
-        .map(|_| rng.gen_range(0..range))
-        .collect_vec();
+        charts
+            .par_iter()
+            .filter(|(chart, _)| experiment.output.draws(*chart))
+            .try_for_each(|(_, draw)| draw())?;
 
-    let mut final_path = base_path.clone();
-    final_path.push(format!("xoshiro256plusplus_{}_{}_rng", range, point_count));
+        let (report, results, fitness) = report_tests(run, &points, &analysis_options)?;
 
-    plot(&final_path, &point_vec)
+        let data = RunData {
+            metadata: &options.metadata,
+            points: &points,
+            results: &results,
+            significance: analysis_options.significance,
+            fitness: &fitness,
+        };
+
+        for format in &experiment.output.export {
+            data.export(&base_path.join(&stem), *format)?;
+        }
+
+        Ok(Report {
+            text: report,
+            stem,
+            score: fitness.score(),
+        })
+    });
+
+    print_reports(reports)
 }
 
-fn run() -> Result<(), Error> {
-    if let Some(base_dirs) = BaseDirs::new() {
-        let base_path = PathBuf::from(base_dirs.home_dir());
-        let point_count = 10000;
-        let range = 10000;
-
-        sequence_rng_plot(&base_path, range, point_count)?;
-        standard_rng_plot(&base_path, range, point_count)?;
-        xorshift_rng_plot(&base_path, range, point_count)?;
-        xoshiro256plusplus_rng_plot(&base_path, range, point_count)?;
+/// Prints the p-value of each test and the fitness score for every row, with failing p-values
+/// marked.
+fn print_summary(rows: &[SummaryRow], significance: f64) {
+    let Some(first) = rows.first() else {
+        return;
+    };
+
+    let widths = first
+        .results
+        .iter()
+        .map(|result| result.name.len().max(8))
+        .collect::<Vec<_>>();
+
+    print!("{:<22} {:>10} {:>10}", "generator", "range", "points");
+
+    for (result, width) in first.results.iter().zip(&widths) {
+        print!("  {:>width$}", result.name, width = width);
+    }
+
+    println!("  {:>8}", "score");
+
+    for row in rows {
+        print!(
+            "{:<22} {:>10} {:>10}",
+            row.generator, row.range, row.point_count
+        );
+
+        for (result, width) in row.results.iter().zip(&widths) {
+            let marker = if result.passed(significance) {
+                " "
+            } else {
+                "*"
+            };
+            print!(
+                "  {:>width$}",
+                format!("{:.4}{}", result.p_value, marker),
+                width = width
+            );
+        }
+
+        println!("  {:>8.3}", row.score);
+    }
+
+    println!(
+        "p-values marked * fail at a significance of {}",
+        significance
+    );
+}
+
+fn run_sweep(registry: &Registry, args: &SweepArgs) -> Result<(), Error> {
+    let (experiment, output) = chart_experiment(
+        registry,
+        &args.sample,
+        &args.analysis,
+        &args.chart,
+        Vec::new(),
+    )?;
+
+    let analysis_options = experiment.analysis.options();
+    let base_path = &output.base_path;
+
+    let ranges = experiment.ranges();
+    let point_counts = experiment.point_counts();
+    let join = |values: &[u64]| {
+        values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    let selected = registry.select(&experiment.generators, experiment.seed)?;
+
+    // Each generator's grid is drawn separately, with its cells drawn at the same time too. Both
+    // are collected in order so the output doesn't depend on the number of jobs, and a generator
+    // failing leaves the rest to finish
+    let sweeps = selected
+        .par_iter()
+        .map(|selected| -> (Vec<SummaryRow>, Vec<Error>) {
+            let mut metadata = selected.generator_metadata();
+            metadata.push(("Ranges", join(&ranges)));
+            metadata.push(("Points", join(&point_counts)));
+
+            let options = output.render_options(metadata);
+
+            // A row of the grid per range, and a column per point count
+            let mut grid = Grid::new(
+                &base_path.join(format!("{}_sweep", selected.label())),
+                point_counts.len(),
+                &options,
+            );
+
+            let runs = ranges
+                .iter()
+                .flat_map(|range| {
+                    point_counts.iter().map(|point_count| Run {
+                        selected: *selected,
+                        range: *range,
+                        point_count: *point_count,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let draw_grid = experiment.output.draws(Chart::Series);
+
+            let cells = each_run(&runs, |run, _| {
+                let points = run.samples();
+                let cell = if draw_grid {
+                    Some(grid.cell(
+                        &points,
+                        &format!("{} points in 0..{}", run.point_count, run.range),
+                        &options,
+                    )?)
+                } else {
+                    None
+                };
+
+                let (results, _) = run_tests(run, &points, &analysis_options)?;
+
+                Ok((
+                    cell,
+                    SummaryRow {
+                        generator: selected.label(),
+                        range: run.range,
+                        point_count: run.point_count,
+                        results,
+                        score: fitness(&points, run.range).score(),
+                    },
+                ))
+            });
+
+            let (cells, mut failures) = partition(cells);
+            let mut rows = Vec::new();
+
+            for (cell, row) in cells {
+                if let Some(cell) = cell {
+                    grid.push(cell);
+                }
+                rows.push(row);
+            }
+
+            // A grid with cells missing would put the rest in the wrong places
+            if draw_grid && failures.is_empty() {
+                if let Err(e) = grid.write(&options) {
+                    failures
+                        .push(Error::from(e).context(format!("{} sweep failed", selected.label())));
+                }
+            }
+
+            (rows, failures)
+        })
+        .collect::<Vec<_>>();
+
+    let (rows, failures): (Vec<_>, Vec<_>) = sweeps.into_iter().unzip();
+    let failed = failures
+        .iter()
+        .filter(|failures| !failures.is_empty())
+        .count();
+    let rows = rows.into_iter().flatten().collect::<Vec<_>>();
+
+    print_summary(&rows, analysis_options.significance);
+    write_summary_csv(
+        &base_path.join("sweep_summary.csv"),
+        &rows,
+        analysis_options.significance,
+    )?;
+
+    if failed == 0 {
+        Ok(())
     } else {
-        return Err(anyhow!("Unable to determine base dirs"));
+        print_failures(&failures.into_iter().flatten().collect::<Vec<_>>());
+        Err(anyhow!(
+            "{} of {} generators failed",
+            failed,
+            selected.len()
+        ))
+    }
+}
+
+fn run_stream(registry: &Registry, args: &StreamArgs) -> Result<(), Error> {
+    let (experiment, output) = chart_experiment(
+        registry,
+        &args.sample,
+        &args.analysis,
+        &args.chart,
+        Vec::new(),
+    )?;
+
+    let analysis_options = experiment.analysis.options();
+    let base_path = &output.base_path;
+    let runs = experiment.runs(registry)?;
+
+    let reports = each_run(&runs, |run, stem| {
+        let summary = stream::analyse(
+            run.stream(),
+            run.range,
+            &analysis_options,
+            args.series_buckets,
+        )?;
+        let options = output.render_options(run.metadata());
+
+        if experiment.output.draws(Chart::Series) {
+            plot_series(&base_path.join(&stem), &summary.series, &options)?;
+        }
+
+        if experiment.output.draws(Chart::Histogram) {
+            plot_bins(
+                &base_path.join(format!("{}_histogram", stem)),
+                &summary.histogram,
+                run.range,
+                &options,
+            )?;
+        }
+
+        Ok(Report {
+            text: report_stream(run, &summary, &analysis_options)?,
+            stem,
+            score: summary.fitness.score(),
+        })
+    });
+
+    print_reports(reports)
+}
+
+fn run_test(registry: &Registry, args: &TestArgs) -> Result<(), Error> {
+    let experiment = args.sample.experiment(
+        registry,
+        Experiment {
+            analysis: args.analysis.config(),
+            ..Default::default()
+        },
+    )?;
+
+    let analysis_options = experiment.analysis.options();
+    let runs = experiment.runs(registry)?;
+
+    let reports = each_run(&runs, |run, stem| {
+        let points = run.samples();
+        let (report, _, fitness) = report_tests(run, &points, &analysis_options)?;
+
+        Ok(Report {
+            text: report,
+            stem,
+            score: fitness.score(),
+        })
+    });
+
+    print_reports(reports)
+}
+
+fn run() -> Result<(), Error> {
+    let cli = Cli::parse();
+    logging::init(cli.verbose, cli.log_config.as_deref())?;
+
+    if let Some(jobs) = cli.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()?;
+    }
+
+    let registry = Registry::default();
+
+    match &cli.command {
+        Command::Plot(args) => run_plot(&registry, args)?,
+        Command::Test(args) => run_test(&registry, args)?,
+        Command::Sweep(args) => run_sweep(&registry, args)?,
+        Command::Stream(args) => run_stream(&registry, args)?,
+        Command::ListGenerators => {
+            for generator in registry.iter() {
+                println!(
+                    "{:<22} {:<10} {}",
+                    generator.name(),
+                    if generator.seedable() { "seedable" } else { "" },
+                    generator.description()
+                );
+            }
+        }
     }
 
     Ok(())
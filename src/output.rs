@@ -0,0 +1,47 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use chrono::Local;
+use directories::ProjectDirs;
+use log::info;
+
+/// Directory output goes in when none is given, under the platform's data directory.
+pub fn default_output_dir() -> Result<PathBuf, Error> {
+    ProjectDirs::from("com", "GrumpyMetalGuy", "procedural_fitness")
+        .map(|dirs| dirs.data_dir().join("runs"))
+        .ok_or_else(|| anyhow!("Unable to determine project dirs"))
+}
+
+/// Creates a new directory for a single run under `output_dir`, creating `output_dir` too if need
+/// be.
+///
+/// The directory is named `run_id`, or the current local time if there isn't one. If that's
+/// already taken a counter is added to the end, so one run never overwrites another's output.
+pub fn create_run_dir(output_dir: &Path, run_id: Option<&str>) -> Result<PathBuf, Error> {
+    fs::create_dir_all(output_dir)?;
+
+    let name = match run_id {
+        Some(run_id) => run_id.to_string(),
+        None => Local::now().format("%Y%m%d_%H%M%S").to_string(),
+    };
+
+    for attempt in 0.. {
+        let run_dir = match attempt {
+            0 => output_dir.join(&name),
+            _ => output_dir.join(format!("{}_{}", name, attempt)),
+        };
+
+        match fs::create_dir(&run_dir) {
+            Ok(()) => {
+                info!("Created run directory {}", run_dir.display());
+                return Ok(run_dir);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    unreachable!()
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use png::ColorType;
+
+use crate::analysis::bits::BitStats;
+use crate::error::FitnessError;
+use crate::plot::{write_raster, RenderOptions};
+
+/// z-score at which a cell reaches full colour.
+const SATURATION: f64 = 4.;
+
+/// Rows of blank pixels between the two halves of the heatmap.
+const GAP: u32 = 16;
+
+/// Draws a heatmap of the bias of each bit, from `blocks` of consecutive outputs, and writes it as a
+/// PNG to `full_path`. This is always a PNG, whatever the output format.
+///
+/// Each column is one of the bits counted, most significant on the left, and each row a block of
+/// outputs. The top half shows how often each bit is set and the bottom half how often it changes
+/// between outputs. White is unbiased, red too often and blue too rarely, reaching full colour at
+/// four standard deviations.
+pub fn plot_bit_heatmap(
+    full_path: &Path,
+    blocks: &[BitStats],
+    options: &RenderOptions,
+) -> Result<(), FitnessError> {
+    if blocks.is_empty() {
+        return Err(FitnessError::EmptyInput {
+            path: full_path.to_path_buf(),
+        });
+    }
+
+    // Sized in usize, as a lot of blocks makes for more pixels than fit in a u32
+    let rows = blocks.len();
+    let columns = blocks[0].bits as usize;
+    let gap = GAP as usize;
+    let cell_width = (options.style.width as usize / columns.max(1)).max(1);
+    let cell_height = (options.style.height.saturating_sub(GAP) as usize / (2 * rows)).max(1);
+
+    let width = cell_width * columns;
+    let half_height = cell_height * rows;
+    let height = half_height * 2 + gap;
+
+    let too_large = || FitnessError::Render {
+        path: full_path.to_path_buf(),
+        message: format!("{} blocks make the heatmap too tall", rows),
+    };
+    let png_width = u32::try_from(width).map_err(|_| too_large())?;
+    let png_height = u32::try_from(height).map_err(|_| too_large())?;
+
+    let mut pixels = vec![255u8; width * height * 3];
+
+    let mut fill = |column: usize, top: usize, z: f64| {
+        let colour = diverging(z);
+
+        for y in top..top + cell_height {
+            for x in column * cell_width..(column + 1) * cell_width {
+                let i = (y * width + x) * 3;
+                pixels[i..i + 3].copy_from_slice(&colour);
+            }
+        }
+    };
+
+    for (row, block) in blocks.iter().enumerate() {
+        let top = row * cell_height;
+
+        for bit in block.counted() {
+            let column = 63 - bit;
+            fill(column, top, block.ones_z_score(bit));
+            fill(column, half_height + gap + top, block.flips_z_score(bit));
+        }
+    }
+
+    write_raster(
+        full_path,
+        png_width,
+        png_height,
+        ColorType::Rgb,
+        &pixels,
+        &options.metadata,
+    )
+}
+
+/// Maps a z-score to white when it's zero, fading to red as it gets larger or blue as it gets smaller.
+fn diverging(z: f64) -> [u8; 3] {
+    let t = (z / SATURATION).clamp(-1., 1.);
+    let fade = (255. * (1. - t.abs())) as u8;
+
+    if t >= 0. {
+        [255, fade, fade]
+    } else {
+        [fade, fade, 255]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::style::Style;
+    use crate::plot::OutputFormat;
+    use rand::rngs::mock::StepRng;
+
+    #[test]
+    fn draws_a_column_per_counted_bit_and_a_row_per_block() {
+        let dir =
+            std::env::temp_dir().join(format!("procedural_fitness_bits_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let options = RenderOptions {
+            style: Style {
+                width: 100,
+                height: 200,
+                ..Style::default()
+            },
+            format: OutputFormat::Png,
+            metadata: Vec::new(),
+        };
+
+        // More blocks than there are rows of pixels, so each gets a single row in each half
+        let blocks = BitStats::blocks_from_rng(&mut StepRng::new(0, 1 << 49), 2000, 1000, 15);
+        plot_bit_heatmap(&dir.join("bits"), &blocks, &options).unwrap();
+
+        let file = std::fs::File::open(dir.join("bits.png")).unwrap();
+        let reader = png::Decoder::new(file).read_info().unwrap();
+        assert_eq!(reader.info().width, 6 * 15);
+        assert_eq!(reader.info().height, 2 * 1000 + GAP);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
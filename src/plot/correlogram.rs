@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use plotlib::repr::Plot;
+use plotlib::style::LineStyle;
+use plotlib::view::ContinuousView;
+
+use crate::analysis::correlation::autocorrelation_bound;
+use crate::error::FitnessError;
+use crate::plot::{write_chart, RenderOptions};
+
+/// Plots the autocorrelation at each lag, as returned from [`autocorrelation`], along with the band
+/// that truly random points would stay within 95% of the time, and writes the result to
+/// `full_path` in each format `options` asks for.
+///
+/// [`autocorrelation`]: crate::analysis::correlation::autocorrelation
+pub fn plot_correlogram(
+    full_path: &Path,
+    acf: &[f64],
+    point_count: usize,
+    options: &RenderOptions,
+) -> Result<(), FitnessError> {
+    if acf.is_empty() {
+        return Err(FitnessError::EmptyInput {
+            path: full_path.to_path_buf(),
+        });
+    }
+
+    let style = &options.style;
+    let bound = autocorrelation_bound(point_count);
+    let max_lag = acf.len() as f64 + 1.;
+
+    // Leave a bit of room above the largest correlation or the band, whichever is bigger
+    let y_max = acf.iter().fold(bound, |max, r| max.max(r.abs())) * 1.1;
+
+    let mut v = ContinuousView::new()
+        .x_range(0., max_lag)
+        .y_range(-y_max, y_max)
+        .x_label("Lag")
+        .y_label("Autocorrelation");
+
+    // A stem from zero up to the correlation at each lag
+    for (i, r) in acf.iter().enumerate() {
+        let lag = (i + 1) as f64;
+
+        v = v.add(
+            Plot::new(vec![(lag, 0.), (lag, *r)])
+                .line_style(LineStyle::new().colour(&style.series_colour).width(3.)),
+        );
+    }
+
+    for y in [-bound, 0., bound] {
+        v = v.add(
+            Plot::new(vec![(0., y), (max_lag, y)])
+                .line_style(LineStyle::new().colour(&style.walk_colour).width(2.)),
+        );
+    }
+
+    write_chart(full_path, &v, options)
+}
@@ -0,0 +1,150 @@
+use crate::plot::style::Style;
+
+/// Side of each cell of density shading, in pixels.
+const DENSITY_CELL: u32 = 16;
+
+/// Space plotlib leaves around the axes of a chart, in pixels across and down.
+const MARGIN: (u32, u32) = (120, 60);
+
+/// Largest-Triangle-Three-Buckets downsampling of `points`, which must be in order of x, down to
+/// `threshold` points.
+///
+/// The first and last points are always kept. The rest are split into equal buckets, and from each
+/// the point making the largest triangle with the one picked before it and the average of the next
+/// bucket is kept, which follows the shape of the series far better than picking points evenly.
+pub fn lttb(points: &[(f64, f64)], threshold: usize) -> Vec<(f64, f64)> {
+    if threshold >= points.len() || threshold < 3 {
+        return points.to_vec();
+    }
+
+    let bucket_size = (points.len() - 2) as f64 / (threshold - 2) as f64;
+    let bucket_start = |bucket: usize| (bucket as f64 * bucket_size) as usize + 1;
+
+    let mut sampled = Vec::with_capacity(threshold);
+    let mut picked = points[0];
+    sampled.push(picked);
+
+    for bucket in 0..threshold - 2 {
+        let next = &points[bucket_start(bucket + 1)..bucket_start(bucket + 2).min(points.len())];
+        let next_x = next.iter().map(|point| point.0).sum::<f64>() / next.len() as f64;
+        let next_y = next.iter().map(|point| point.1).sum::<f64>() / next.len() as f64;
+
+        let area = |point: &(f64, f64)| {
+            ((picked.0 - next_x) * (point.1 - picked.1)
+                - (picked.0 - point.0) * (next_y - picked.1))
+                .abs()
+        };
+
+        picked = points[bucket_start(bucket)..bucket_start(bucket + 1)]
+            .iter()
+            .copied()
+            .max_by(|a, b| area(a).total_cmp(&area(b)))
+            .unwrap_or(picked);
+
+        sampled.push(picked);
+    }
+
+    sampled.extend(points.last());
+    sampled
+}
+
+/// Shades `svg`, a chart of `points` against time drawn by plotlib at `width` by `height`, by how
+/// many points land in each cell of a grid over it. The shading goes under everything else, in the
+/// series colour, with cells more opaque the more points they have.
+///
+/// `svg` is returned as is unless the style asks for shading and there are more points than its
+/// downsampling threshold.
+pub(crate) fn shade_density(
+    mut svg: String,
+    points: &[u64],
+    style: &Style,
+    width: u32,
+    height: u32,
+) -> String {
+    let Some(max_y) = points.iter().max() else {
+        return svg;
+    };
+
+    if !style.density || points.len() <= style.downsample_threshold {
+        return svg;
+    }
+
+    let face_width = width.saturating_sub(MARGIN.0).max(1);
+    let face_height = height.saturating_sub(MARGIN.1).max(1);
+    let columns = (face_width / DENSITY_CELL).max(1) as usize;
+    let rows = (face_height / DENSITY_CELL).max(1) as usize;
+
+    let mut counts = vec![0u64; columns * rows];
+
+    for (i, point) in points.iter().enumerate() {
+        let column = i * columns / points.len();
+        let row = ((*point as u128 * rows as u128) / (*max_y as u128 + 1)) as usize;
+        counts[row * columns + column] += 1;
+    }
+
+    let most = counts.iter().max().copied().unwrap_or(0).max(1);
+    let cell_width = face_width as f64 / columns as f64;
+    let cell_height = face_height as f64 / rows as f64;
+
+    // plotlib puts the origin 60% of the way across and up each margin
+    let left = 0.6 * MARGIN.0 as f64;
+    let bottom = height as f64 - 0.6 * MARGIN.1 as f64;
+
+    let mut shading = format!("\n<g fill=\"{}\">", style.series_colour);
+
+    for (i, count) in counts.iter().enumerate().filter(|(_, count)| **count > 0) {
+        let (row, column) = (i / columns, i % columns);
+
+        shading.push_str(&format!(
+            "\n<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill-opacity=\"{:.3}\"/>",
+            left + column as f64 * cell_width,
+            bottom - (row + 1) as f64 * cell_height,
+            cell_width,
+            cell_height,
+            *count as f64 / most as f64
+        ));
+    }
+
+    shading.push_str("\n</g>");
+
+    // Straight after the opening tag, so the shading is drawn first
+    if let Some(end) = svg.find('>') {
+        svg.insert_str(end + 1, &shading);
+    }
+
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lttb_keeps_short_series_as_is() {
+        let points = [(0., 1.), (1., 3.), (2., 2.)];
+
+        assert_eq!(lttb(&points, 3), points);
+        assert_eq!(lttb(&points, 10), points);
+        assert_eq!(lttb(&points, 2), points);
+    }
+
+    #[test]
+    fn lttb_keeps_the_ends_and_the_peak() {
+        let points = [(0., 0.), (1., 0.), (2., 10.), (3., 0.), (4., 0.)];
+
+        assert_eq!(lttb(&points, 3), [(0., 0.), (2., 10.), (4., 0.)]);
+    }
+
+    #[test]
+    fn lttb_keeps_spikes_in_long_series() {
+        let points = (0..10000)
+            .map(|i| (i as f64, if i % 1000 == 500 { 100. } else { 0. }))
+            .collect::<Vec<_>>();
+
+        let sampled = lttb(&points, 100);
+
+        assert_eq!(sampled.len(), 100);
+        assert!(sampled.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(sampled.iter().filter(|point| point.1 == 100.).count(), 10);
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::FitnessError;
+use crate::plot::downsample::shade_density;
+use crate::plot::{render_svg, time_series_view, write_svg, RenderOptions};
+
+/// A grid of the charts drawn by [`plot`], one per cell, filled in a row at a time.
+///
+/// Each cell is rendered as soon as it's added, so only the finished SVGs are kept rather than the
+/// samples behind them. Cells are half the width and height of a single chart.
+///
+/// [`plot`]: crate::plot::plot
+pub struct Grid {
+    full_path: PathBuf,
+    columns: usize,
+    cell_width: u32,
+    cell_height: u32,
+    cells: Vec<String>,
+}
+
+impl Grid {
+    /// Starts an empty grid `columns` wide, to be written to `full_path`.
+    pub fn new(full_path: &Path, columns: usize, options: &RenderOptions) -> Self {
+        Self {
+            full_path: full_path.to_path_buf(),
+            columns: columns.max(1),
+            cell_width: (options.style.width / 2).max(1),
+            cell_height: (options.style.height / 2).max(1),
+            cells: Vec::new(),
+        }
+    }
+
+    /// Draws `points` in the next cell, with `label` under it.
+    pub fn add(
+        &mut self,
+        points: &[u64],
+        label: &str,
+        options: &RenderOptions,
+    ) -> Result<(), FitnessError> {
+        let cell = self.cell(points, label, options)?;
+        self.push(cell);
+
+        Ok(())
+    }
+
+    /// Draws `points` sized to fit a cell, with `label` under it, without adding it to the grid. Cells
+    /// can be drawn on several threads at once this way, then [`push`]ed in order.
+    ///
+    /// [`push`]: Grid::push
+    pub fn cell(
+        &self,
+        points: &[u64],
+        label: &str,
+        options: &RenderOptions,
+    ) -> Result<String, FitnessError> {
+        let view = time_series_view(&self.full_path, points, &options.style)?.x_label(label);
+        let svg = render_svg(&self.full_path, &view, self.cell_width, self.cell_height)?;
+
+        Ok(shade_density(
+            svg,
+            points,
+            &options.style,
+            self.cell_width,
+            self.cell_height,
+        ))
+    }
+
+    /// Adds a cell drawn by [`cell`] to the next free place in the grid.
+    ///
+    /// [`cell`]: Grid::cell
+    pub fn push(&mut self, cell: String) {
+        self.cells.push(cell);
+    }
+
+    /// Lays the cells out and writes the grid to the path it was created with.
+    pub fn write(self, options: &RenderOptions) -> Result<(), FitnessError> {
+        if self.cells.is_empty() {
+            return Err(FitnessError::EmptyInput {
+                path: self.full_path,
+            });
+        }
+
+        let rows = self.cells.len().div_ceil(self.columns);
+        let width = self.cell_width * self.columns as u32;
+        let height = self.cell_height * rows as u32;
+
+        let mut svg = format!(
+            "<svg viewBox=\"0 0 {} {}\" xmlns=\"http://www.w3.org/2000/svg\">\n",
+            width, height
+        );
+
+        for (i, cell) in self.cells.iter().enumerate() {
+            let x = (i % self.columns) as u32 * self.cell_width;
+            let y = (i / self.columns) as u32 * self.cell_height;
+
+            // Nest each cell's own SVG in place, keeping its view box so it scales to fit
+            svg.push_str(&cell.replacen(
+                "<svg ",
+                &format!(
+                    "<svg x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" ",
+                    x, y, self.cell_width, self.cell_height
+                ),
+                1,
+            ));
+            svg.push('\n');
+        }
+
+        svg.push_str("</svg>");
+
+        write_svg(&self.full_path, svg, options)
+    }
+}
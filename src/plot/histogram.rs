@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use plotlib::repr::Plot;
+use plotlib::style::LineStyle;
+use plotlib::view::ContinuousView;
+
+use crate::analysis::{histogram, Bin};
+use crate::error::FitnessError;
+use crate::plot::{write_chart, RenderOptions};
+
+/// Plots a histogram of `points` over `0..range` with `bins` bins, along with the expected count in
+/// each bin and the band that truly random points would stay within about 95% of the time, and
+/// writes the result to `full_path` in each format `options` asks for.
+pub fn plot_histogram(
+    full_path: &Path,
+    points: &[u64],
+    range: u64,
+    bins: usize,
+    options: &RenderOptions,
+) -> Result<(), FitnessError> {
+    plot_bins(full_path, &histogram(points, range, bins), range, options)
+}
+
+/// Plots a histogram over `0..range` that's already been counted, as for [`plot_histogram`].
+pub fn plot_bins(
+    full_path: &Path,
+    histogram: &[Bin],
+    range: u64,
+    options: &RenderOptions,
+) -> Result<(), FitnessError> {
+    let n = histogram.iter().map(|bin| bin.count).sum::<u64>() as f64;
+
+    if n == 0. {
+        return Err(FitnessError::EmptyInput {
+            path: full_path.to_path_buf(),
+        });
+    }
+
+    // Each bin's count is binomially distributed, with p being the bin's share of the range
+    let expected = histogram
+        .iter()
+        .map(|bin| {
+            let p = bin.width as f64 / range as f64;
+            (n * p, (n * p * (1. - p)).sqrt())
+        })
+        .collect::<Vec<_>>();
+
+    let counts = histogram
+        .iter()
+        .map(|bin| bin.count as f64)
+        .collect::<Vec<_>>();
+    let upper = expected
+        .iter()
+        .map(|(mean, sd)| mean + 2. * sd)
+        .collect::<Vec<_>>();
+    let lower = expected
+        .iter()
+        .map(|(mean, sd)| (mean - 2. * sd).max(0.))
+        .collect::<Vec<_>>();
+    let expected = expected.iter().map(|(mean, _)| *mean).collect::<Vec<_>>();
+
+    let y_max = counts
+        .iter()
+        .chain(&upper)
+        .fold(0., |max: f64, y| max.max(*y))
+        * 1.1;
+
+    // Draw each series as a step outline over the bins
+    let steps = |heights: &[f64]| {
+        let mut outline = Vec::new();
+
+        for (bin, height) in histogram.iter().zip(heights) {
+            outline.push((bin.start as f64, *height));
+            outline.push(((bin.start + bin.width) as f64, *height));
+        }
+
+        outline
+    };
+
+    let style = &options.style;
+    let line = |colour: &str, width: f32| LineStyle::new().colour(colour).width(width);
+
+    let v = ContinuousView::new()
+        .add(Plot::new(steps(&lower)).line_style(line(&style.band_colour, 2.)))
+        .add(Plot::new(steps(&upper)).line_style(line(&style.band_colour, 2.)))
+        .add(Plot::new(steps(&counts)).line_style(line(&style.series_colour, 3.)))
+        .add(Plot::new(steps(&expected)).line_style(line(&style.walk_colour, 3.)))
+        .x_range(0., range as f64)
+        .y_range(0., y_max)
+        .x_label("Value")
+        .y_label("Frequency");
+
+    write_chart(full_path, &v, options)
+}
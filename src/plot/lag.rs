@@ -0,0 +1,150 @@
+use std::path::Path;
+
+use plotlib::repr::Plot;
+use plotlib::style::{LineStyle, PointStyle};
+use plotlib::view::ContinuousView;
+
+use crate::error::FitnessError;
+use crate::plot::style::Style;
+use crate::plot::{write_chart, RenderOptions};
+
+/// Angles, in degrees, to look at a 3D lag plot from.
+#[derive(Clone, Copy, Debug)]
+pub struct LagView {
+    /// Rotation about the vertical axis.
+    pub azimuth: f64,
+
+    /// Tilt towards or away from the viewer.
+    pub elevation: f64,
+}
+
+impl Default for LagView {
+    fn default() -> Self {
+        Self {
+            azimuth: 30.,
+            elevation: 20.,
+        }
+    }
+}
+
+impl LagView {
+    /// Projects a point in the unit cube onto the screen, with the cube centred on the origin.
+    fn project(&self, (x, y, z): (f64, f64, f64)) -> (f64, f64) {
+        let (sin_a, cos_a) = self.azimuth.to_radians().sin_cos();
+        let (sin_e, cos_e) = self.elevation.to_radians().sin_cos();
+        let (x, y, z) = (x - 0.5, y - 0.5, z - 0.5);
+
+        let rotated_x = x * cos_a - y * sin_a;
+        let rotated_y = x * sin_a + y * cos_a;
+
+        (rotated_x, z * cos_e - rotated_y * sin_e)
+    }
+}
+
+/// Lag plots have far more points packed into the same space, so use smaller markers.
+fn point_style(style: &Style) -> PointStyle {
+    PointStyle::new()
+        .marker(style.marker.point_marker())
+        .colour(&style.series_colour)
+        .size(style.marker_size * 2. / 3.)
+}
+
+/// Plots each point against the one after it, and writes the result to `full_path` in each format
+/// `options` asks for.
+///
+/// Generators whose consecutive outputs are related, like LCGs, show up as points falling on a
+/// small number of lines.
+pub fn plot_lag_2d(
+    full_path: &Path,
+    points: &[u64],
+    range: u64,
+    options: &RenderOptions,
+) -> Result<(), FitnessError> {
+    let pairs = points
+        .windows(2)
+        .map(|pair| (pair[0] as f64, pair[1] as f64))
+        .collect::<Vec<_>>();
+
+    if pairs.is_empty() {
+        return Err(FitnessError::EmptyInput {
+            path: full_path.to_path_buf(),
+        });
+    }
+
+    let v = ContinuousView::new()
+        .add(Plot::new(pairs).point_style(point_style(&options.style)))
+        .x_range(0., range as f64)
+        .y_range(0., range as f64)
+        .x_label("x[i]")
+        .y_label("x[i + 1]");
+
+    write_chart(full_path, &v, options)
+}
+
+/// Plots each run of three consecutive points as a point in a cube, projected onto the screen as
+/// seen from `view`, and writes the result to `full_path` in each format `options` asks for.
+///
+/// Generators like RANDU, whose triples all lie on a handful of planes, show this as bands of
+/// points when viewed from the right angle.
+pub fn plot_lag_3d(
+    full_path: &Path,
+    points: &[u64],
+    range: u64,
+    view: LagView,
+    options: &RenderOptions,
+) -> Result<(), FitnessError> {
+    let scale = range as f64;
+
+    let triples = points
+        .windows(3)
+        .map(|triple| {
+            view.project((
+                triple[0] as f64 / scale,
+                triple[1] as f64 / scale,
+                triple[2] as f64 / scale,
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    if triples.is_empty() {
+        return Err(FitnessError::EmptyInput {
+            path: full_path.to_path_buf(),
+        });
+    }
+
+    // The unit cube's diagonal is the furthest any point can be projected from the centre
+    let extent = 3f64.sqrt() / 2.;
+
+    let mut v = ContinuousView::new()
+        .add(Plot::new(triples).point_style(point_style(&options.style)))
+        .x_range(-extent, extent)
+        .y_range(-extent, extent);
+
+    // Outline the cube so it's clear which way it's facing
+    let corners = [0., 1.];
+
+    for (a, b) in [(0, 1), (0, 2), (1, 2)] {
+        for (c, d) in corners
+            .iter()
+            .flat_map(|c| corners.iter().map(move |d| (*c, *d)))
+        {
+            let edge = |end: f64| {
+                let mut corner = [0.; 3];
+                corner[a] = c;
+                corner[b] = d;
+                corner[3 - a - b] = end;
+                view.project((corner[0], corner[1], corner[2]))
+            };
+
+            v = v.add(
+                Plot::new(vec![edge(0.), edge(1.)]).line_style(
+                    LineStyle::new()
+                        .colour(&options.style.walk_colour)
+                        .width(2.),
+                ),
+            );
+        }
+    }
+
+    write_chart(full_path, &v, options)
+}
@@ -0,0 +1,473 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use charts_rs::svg_to_png;
+use log::info;
+use plotlib::page::Page;
+use plotlib::repr::Plot;
+use plotlib::style::PointStyle;
+use plotlib::view::{ContinuousView, View};
+use png::{BitDepth, ColorType, Encoder};
+use serde::Deserialize;
+
+use crate::error::FitnessError;
+
+use crate::fitness::fitness_walk;
+use crate::plot::downsample::{lttb, shade_density};
+use crate::plot::style::{Downsample, Style};
+use crate::stream::Series;
+
+pub mod bits;
+pub mod correlogram;
+pub mod downsample;
+pub mod grid;
+pub mod histogram;
+pub mod lag;
+pub mod noise;
+pub mod style;
+
+/// Points of a chart as x, y pairs.
+type Points = Vec<(f64, f64)>;
+
+/// Which files each chart is written to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Png,
+    Svg,
+    Both,
+}
+
+impl OutputFormat {
+    fn png(&self) -> bool {
+        matches!(self, Self::Png | Self::Both)
+    }
+
+    fn svg(&self) -> bool {
+        matches!(self, Self::Svg | Self::Both)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(Self::Png),
+            "svg" => Ok(Self::Svg),
+            "both" => Ok(Self::Both),
+            _ => Err(anyhow!(
+                "Unknown output format '{}', expected png, svg or both",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Png => write!(f, "png"),
+            Self::Svg => write!(f, "svg"),
+            Self::Both => write!(f, "both"),
+        }
+    }
+}
+
+/// A chart that can be chosen to draw for each run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Chart {
+    Series,
+    Correlogram,
+    Histogram,
+    Lag2d,
+    Lag3d,
+    Noise,
+    Bits,
+}
+
+impl FromStr for Chart {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "series" => Ok(Self::Series),
+            "correlogram" => Ok(Self::Correlogram),
+            "histogram" => Ok(Self::Histogram),
+            "lag2d" => Ok(Self::Lag2d),
+            "lag3d" => Ok(Self::Lag3d),
+            "noise" => Ok(Self::Noise),
+            "bits" => Ok(Self::Bits),
+            _ => Err(anyhow!(
+                "Unknown chart '{}', expected series, correlogram, histogram, lag2d, lag3d, noise \
+                 or bits",
+                s
+            )),
+        }
+    }
+}
+
+/// Settings shared by every chart.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// How each chart looks.
+    pub style: Style,
+
+    /// Which files to write each chart to.
+    pub format: OutputFormat,
+
+    /// Key/value pairs describing the run, stored as text chunks in PNGs.
+    pub metadata: Vec<(&'static str, String)>,
+}
+
+/// Plots `points` against time along with their fitness walk, and writes the result to `full_path`.
+///
+/// Beyond the style's downsampling threshold both are downsampled as it says, and the points are
+/// shaded by density underneath if it asks for that.
+pub fn plot(full_path: &Path, points: &[u64], options: &RenderOptions) -> Result<(), FitnessError> {
+    let style = &options.style;
+    let svg = render_svg(
+        full_path,
+        &time_series_view(full_path, points, style)?,
+        style.width,
+        style.height,
+    )?;
+
+    write_svg(
+        full_path,
+        shade_density(svg, points, style, style.width, style.height),
+        options,
+    )
+}
+
+/// Plots a series downsampled while streaming against time, along with its fitness walk, and writes
+/// the result to `full_path`. Each bucket is drawn as its smallest and largest sample and walk
+/// position.
+pub fn plot_series(
+    full_path: &Path,
+    series: &Series,
+    options: &RenderOptions,
+) -> Result<(), FitnessError> {
+    let max_y = series
+        .buckets()
+        .iter()
+        .map(|bucket| bucket.max)
+        .max()
+        .ok_or_else(|| FitnessError::EmptyInput {
+            path: full_path.to_path_buf(),
+        })?;
+
+    let (time_series, walk) = bucket_points(series);
+    let view = series_view(
+        time_series,
+        walk,
+        series.len() as f64,
+        max_y as f64,
+        &options.style,
+    );
+
+    write_chart(full_path, &view, options)
+}
+
+/// The view drawn by [`plot`], without the density shading. `full_path` is only used to say which
+/// chart had no points.
+pub(crate) fn time_series_view(
+    full_path: &Path,
+    points: &[u64],
+    style: &Style,
+) -> Result<ContinuousView, FitnessError> {
+    let max_y = points
+        .iter()
+        .max()
+        .ok_or_else(|| FitnessError::EmptyInput {
+            path: full_path.to_path_buf(),
+        })?;
+
+    let walk = fitness_walk(points);
+    let threshold = style.downsample_threshold;
+
+    let (time_series, walk) = match style.downsample {
+        _ if points.len() <= threshold => (enumerate(points), enumerate(&walk)),
+        Downsample::None => (enumerate(points), enumerate(&walk)),
+
+        // Two points a bucket, and between a quarter and half the threshold of buckets
+        Downsample::MinMax => {
+            let mut series = Series::new(threshold / 4);
+
+            for (point, position) in points.iter().zip(&walk) {
+                series.push(*point, *position);
+            }
+
+            bucket_points(&series)
+        }
+
+        Downsample::Lttb => (
+            lttb(&enumerate(points), threshold),
+            lttb(&enumerate(&walk), threshold),
+        ),
+    };
+
+    Ok(series_view(
+        time_series,
+        walk,
+        points.len() as f64,
+        *max_y as f64,
+        style,
+    ))
+}
+
+/// Turns a sequence into a vec of tuples of x, y, with x counting up from 0.
+fn enumerate<T: Copy + Into<i128>>(values: &[T]) -> Points {
+    values
+        .iter()
+        .enumerate()
+        .map(|(x, y)| (x as f64, (*y).into() as f64))
+        .collect()
+}
+
+/// The smallest and largest point and walk position of each bucket of `series`, all at the
+/// bucket's start.
+fn bucket_points(series: &Series) -> (Points, Points) {
+    let buckets = series.buckets();
+
+    let time_series = buckets
+        .iter()
+        .flat_map(|bucket| [(bucket.start, bucket.min), (bucket.start, bucket.max)])
+        .map(|(x, y)| (x as f64, y as f64))
+        .collect();
+
+    let walk = buckets
+        .iter()
+        .flat_map(|bucket| {
+            [
+                (bucket.start, bucket.walk_min),
+                (bucket.start, bucket.walk_max),
+            ]
+        })
+        .map(|(x, y)| (x as f64, y as f64))
+        .collect();
+
+    (time_series, walk)
+}
+
+/// Draws `time_series` as points, along with the fitness walk `walk` if the style shows it,
+/// over `0..max_x` and `0..max_y`, or `0..1` if every point is 0.
+fn series_view(
+    time_series: Vec<(f64, f64)>,
+    walk: Vec<(f64, f64)>,
+    max_x: f64,
+    max_y: f64,
+    style: &Style,
+) -> ContinuousView {
+    let time_series: Plot = Plot::new(time_series).point_style(
+        PointStyle::new()
+            .marker(style.marker.point_marker())
+            .colour(&style.series_colour)
+            .size(style.marker_size),
+    );
+
+    // The 'view' describes what set of data is drawn
+    let mut v = ContinuousView::new().add(time_series);
+
+    // Now plot the fitness indicator
+    if style.show_walk {
+        let fitness_indicator: Plot = Plot::new(walk).point_style(
+            PointStyle::new()
+                .marker(style.walk_marker.point_marker())
+                .colour(&style.walk_colour)
+                .size(style.walk_size),
+        );
+
+        v = v.add(fitness_indicator);
+    }
+
+    // plotlib rejects an empty range, which a generator stuck at 0 would otherwise give
+    v.x_range(0., max_x.max(1.))
+        .y_range(0., max_y.max(1.))
+        .x_label("Time")
+        .y_label("Value")
+}
+
+/// Renders a page with a single view and saves it to `full_path`, as an SVG, a PNG or both. Each
+/// piece of metadata is stored in the PNG as a text chunk.
+fn write_chart(
+    full_path: &Path,
+    view: &dyn View,
+    options: &RenderOptions,
+) -> Result<(), FitnessError> {
+    let svg = render_svg(full_path, view, options.style.width, options.style.height)?;
+    write_svg(full_path, svg, options)
+}
+
+/// Renders a page with a single view as an SVG, as plotlib draws it.
+pub(crate) fn render_svg(
+    full_path: &Path,
+    view: &dyn View,
+    width: u32,
+    height: u32,
+) -> Result<String, FitnessError> {
+    Page::single(view)
+        .dimensions(width, height)
+        .to_svg()
+        .map(|svg| svg.to_string())
+        .map_err(|e| FitnessError::Render {
+            path: full_path.to_path_buf(),
+            message: e.to_string(),
+        })
+}
+
+/// Applies the style's colours to an SVG drawn by plotlib, and saves it as an SVG, a PNG or both.
+pub(crate) fn write_svg(
+    full_path: &Path,
+    svg: String,
+    options: &RenderOptions,
+) -> Result<(), FitnessError> {
+    let svg = options.style.apply_to_svg(svg);
+
+    if options.format.svg() {
+        write_file(&full_path.with_extension("svg"), svg.as_bytes())?;
+    }
+
+    if options.format.png() {
+        let png_path = full_path.with_extension("png");
+        let png = svg_to_png(&svg).map_err(|source| FitnessError::Rasterise {
+            path: png_path.clone(),
+            source,
+        })?;
+
+        write_file(&png_path, &add_text_chunks(png, &options.metadata))?;
+    }
+
+    Ok(())
+}
+
+/// Encodes `pixels`, eight bits per channel, as a `width` by `height` PNG and saves it to
+/// `full_path` with each piece of metadata stored as a text chunk.
+pub(crate) fn write_raster(
+    full_path: &Path,
+    width: u32,
+    height: u32,
+    colour: ColorType,
+    pixels: &[u8],
+    metadata: &[(&str, String)],
+) -> Result<(), FitnessError> {
+    let png_path = full_path.with_extension("png");
+    let encode_error = |source| FitnessError::Encode {
+        path: png_path.clone(),
+        source,
+    };
+
+    let mut encoded = Vec::new();
+    let mut encoder = Encoder::new(&mut encoded, width, height);
+    encoder.set_color(colour);
+    encoder.set_depth(BitDepth::Eight);
+    encoder
+        .write_header()
+        .map_err(encode_error)?
+        .write_image_data(pixels)
+        .map_err(encode_error)?;
+
+    write_file(&png_path, &add_text_chunks(encoded, metadata))
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), FitnessError> {
+    std::fs::write(path, contents).map_err(|source| FitnessError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    info!("Wrote {}", path.display());
+    Ok(())
+}
+
+/// Inserts a tEXt chunk for each of `metadata` into an encoded PNG, straight after its header.
+pub(crate) fn add_text_chunks(png: Vec<u8>, metadata: &[(&str, String)]) -> Vec<u8> {
+    // The 8 byte signature is always followed by the 25 byte IHDR chunk
+    let header_len = 8 + 25;
+
+    let mut output = Vec::with_capacity(png.len());
+    output.extend_from_slice(&png[..header_len]);
+
+    for (keyword, text) in metadata {
+        let mut chunk = b"tEXt".to_vec();
+        chunk.extend_from_slice(keyword.as_bytes());
+        chunk.push(0);
+        chunk.extend_from_slice(text.as_bytes());
+
+        output.extend_from_slice(&(chunk.len() as u32 - 4).to_be_bytes());
+        output.extend_from_slice(&chunk);
+        output.extend_from_slice(&crc32fast::hash(&chunk).to_be_bytes());
+    }
+
+    output.extend_from_slice(&png[header_len..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_chunks_decode_with_the_image() {
+        let mut encoded = Vec::new();
+        let mut encoder = Encoder::new(&mut encoded, 2, 1);
+        encoder.set_color(ColorType::Grayscale);
+        encoder.set_depth(BitDepth::Eight);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&[0, 255])
+            .unwrap();
+
+        let metadata = [
+            ("Generator", "randu".to_string()),
+            ("Points", "1000".to_string()),
+        ];
+        let png = add_text_chunks(encoded, &metadata);
+
+        // The decoder checks every chunk's length and CRC
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+
+        let text = reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.as_str(), chunk.text.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(text, [("Generator", "randu"), ("Points", "1000")]);
+        assert_eq!(pixels, [0, 255]);
+    }
+
+    #[test]
+    fn draws_a_sample_that_is_all_zero() {
+        let dir =
+            std::env::temp_dir().join(format!("procedural_fitness_zero_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let options = RenderOptions {
+            style: Style::default(),
+            format: OutputFormat::Svg,
+            metadata: Vec::new(),
+        };
+
+        let mut series = Series::new(4);
+
+        for _ in 0..10 {
+            series.push(0, 0);
+        }
+
+        plot(&dir.join("points"), &[0; 10], &options).unwrap();
+        plot_series(&dir.join("series"), &series, &options).unwrap();
+
+        assert!(dir.join("points.svg").exists());
+        assert!(dir.join("series.svg").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
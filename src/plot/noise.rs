@@ -0,0 +1,77 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use png::ColorType;
+use rand::RngCore;
+
+use crate::error::FitnessError;
+use crate::plot::write_raster;
+
+/// How raw generator output is turned into pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseMode {
+    /// One bit per pixel, black for 0 and white for 1.
+    Bits,
+
+    /// One byte per pixel, as a shade of grey.
+    Bytes,
+}
+
+impl FromStr for NoiseMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bits" => Ok(Self::Bits),
+            "bytes" => Ok(Self::Bytes),
+            _ => Err(anyhow!(
+                "Unknown noise mode '{}', expected bits or bytes",
+                s
+            )),
+        }
+    }
+}
+
+/// Fills a `width` by `height` image from the raw output of `rng`, and writes it as a PNG to
+/// `full_path`. This is always a PNG, whatever the output format.
+///
+/// Pixels are filled left to right, top to bottom, taking the bits or bytes of each `next_u64` from
+/// most to least significant. A good generator gives an even, featureless noise, while patterns or
+/// stripes show structure in its output.
+pub fn plot_noise(
+    full_path: &Path,
+    rng: &mut dyn RngCore,
+    mode: NoiseMode,
+    width: u32,
+    height: u32,
+    metadata: &[(&str, String)],
+) -> Result<(), FitnessError> {
+    let pixel_count = width as usize * height as usize;
+    let pixels_per_word = match mode {
+        NoiseMode::Bits => 64,
+        NoiseMode::Bytes => 8,
+    };
+
+    let mut pixels = Vec::with_capacity(pixel_count);
+
+    while pixels.len() < pixel_count {
+        let word = rng.next_u64();
+
+        pixels.extend((0..pixels_per_word).rev().map(|i| match mode {
+            NoiseMode::Bits => ((word >> i) & 1) as u8 * 255,
+            NoiseMode::Bytes => (word >> (i * 8)) as u8,
+        }));
+    }
+
+    pixels.truncate(pixel_count);
+
+    write_raster(
+        full_path,
+        width,
+        height,
+        ColorType::Grayscale,
+        &pixels,
+        metadata,
+    )
+}
@@ -0,0 +1,354 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Error};
+use plotlib::style::PointMarker;
+use serde::Deserialize;
+
+/// A starting set of colours for charts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Dark lines on a transparent background.
+    #[default]
+    Light,
+
+    /// Light lines on a dark background.
+    Dark,
+}
+
+impl FromStr for Theme {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "light" => Ok(Self::Light),
+            "dark" => Ok(Self::Dark),
+            _ => Err(anyhow!("Unknown theme '{}', expected light or dark", s)),
+        }
+    }
+}
+
+impl Display for Theme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Light => write!(f, "light"),
+            Self::Dark => write!(f, "dark"),
+        }
+    }
+}
+
+/// Shape of each point in a scatter chart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Marker {
+    Square,
+    Circle,
+    Cross,
+}
+
+impl Marker {
+    pub(crate) fn point_marker(&self) -> PointMarker {
+        match self {
+            Self::Square => PointMarker::Square,
+            Self::Circle => PointMarker::Circle,
+            Self::Cross => PointMarker::Cross,
+        }
+    }
+}
+
+impl FromStr for Marker {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Self::Square),
+            "circle" => Ok(Self::Circle),
+            "cross" => Ok(Self::Cross),
+            _ => Err(anyhow!(
+                "Unknown marker '{}', expected square, circle or cross",
+                s
+            )),
+        }
+    }
+}
+
+/// How to thin out a time series with too many points to draw each of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Downsample {
+    /// Draw every point regardless.
+    None,
+
+    /// Split the series into buckets and draw the smallest and largest point in each.
+    MinMax,
+
+    /// Largest-Triangle-Three-Buckets, which keeps the points that most change the series' shape.
+    Lttb,
+}
+
+impl FromStr for Downsample {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "minmax" => Ok(Self::MinMax),
+            "lttb" => Ok(Self::Lttb),
+            _ => Err(anyhow!(
+                "Unknown downsampling '{}', expected none, minmax or lttb",
+                s
+            )),
+        }
+    }
+}
+
+/// How charts look.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Style {
+    /// Width of each chart in pixels.
+    pub width: u32,
+
+    /// Height of each chart in pixels.
+    pub height: u32,
+
+    /// Colour filling the whole chart, or `None` to leave it transparent.
+    pub background: Option<String>,
+
+    /// Colour of the axes and labels.
+    pub foreground: String,
+
+    /// Colour of the sampled points, and the main series of other charts.
+    pub series_colour: String,
+
+    /// Colour of the fitness walk, and what's expected on other charts.
+    pub walk_colour: String,
+
+    /// Colour of the band of expected variation on histograms.
+    pub band_colour: String,
+
+    /// Shape of each sampled point.
+    pub marker: Marker,
+
+    /// Size of each sampled point.
+    pub marker_size: f32,
+
+    /// Shape of each step of the fitness walk.
+    pub walk_marker: Marker,
+
+    /// Size of each step of the fitness walk.
+    pub walk_size: f32,
+
+    /// Whether to draw the fitness walk over the sampled points.
+    pub show_walk: bool,
+
+    /// How to thin out the sampled points and fitness walk when there are more than
+    /// `downsample_threshold` of them.
+    pub downsample: Downsample,
+
+    /// Most points of the time series and fitness walk to draw before downsampling them.
+    pub downsample_threshold: usize,
+
+    /// Whether to shade the time series by how many points land in each part of it, when there are
+    /// more than `downsample_threshold` points.
+    pub density: bool,
+}
+
+impl Style {
+    /// The default style for `theme`.
+    pub fn new(theme: Theme) -> Self {
+        let light = Self {
+            width: 1920,
+            height: 1080,
+            background: None,
+            foreground: "black".to_string(),
+            series_colour: "#DD3355".to_string(),
+            walk_colour: "#35C788".to_string(),
+            band_colour: "#AAAAAA".to_string(),
+            marker: Marker::Square,
+            marker_size: 3.,
+            walk_marker: Marker::Circle,
+            walk_size: 4.,
+            show_walk: true,
+            downsample: Downsample::MinMax,
+            downsample_threshold: 4000,
+            density: true,
+        };
+
+        match theme {
+            Theme::Light => light,
+            Theme::Dark => Self {
+                background: Some("#1E1E24".to_string()),
+                foreground: "#E0E0E0".to_string(),
+                series_colour: "#FF5C7A".to_string(),
+                walk_colour: "#4FE0A0".to_string(),
+                band_colour: "#666670".to_string(),
+                ..light
+            },
+        }
+    }
+
+    /// The style for `overrides`, starting from its theme's defaults and replacing anything else
+    /// it sets.
+    pub fn from_overrides(overrides: &StyleOverrides) -> Result<Self, Error> {
+        let base = Self::new(overrides.theme.unwrap_or_default());
+
+        let style = Self {
+            width: overrides.width.unwrap_or(base.width),
+            height: overrides.height.unwrap_or(base.height),
+            background: overrides.background.clone().or(base.background),
+            foreground: overrides.foreground.clone().unwrap_or(base.foreground),
+            series_colour: overrides
+                .series_colour
+                .clone()
+                .unwrap_or(base.series_colour),
+            walk_colour: overrides.walk_colour.clone().unwrap_or(base.walk_colour),
+            band_colour: overrides.band_colour.clone().unwrap_or(base.band_colour),
+            marker: overrides.marker.unwrap_or(base.marker),
+            marker_size: overrides.marker_size.unwrap_or(base.marker_size),
+            walk_marker: overrides.walk_marker.unwrap_or(base.walk_marker),
+            walk_size: overrides.walk_size.unwrap_or(base.walk_size),
+            show_walk: overrides.show_walk.unwrap_or(base.show_walk),
+            downsample: overrides.downsample.unwrap_or(base.downsample),
+            downsample_threshold: overrides
+                .downsample_threshold
+                .unwrap_or(base.downsample_threshold),
+            density: overrides.density.unwrap_or(base.density),
+        };
+
+        style.validate()?;
+        Ok(style)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.width == 0 || self.height == 0 {
+            return Err(anyhow!("Charts must be at least one pixel wide and high"));
+        }
+
+        if self.downsample_threshold < 4 {
+            return Err(anyhow!(
+                "Invalid downsample_threshold {}, expected at least 4",
+                self.downsample_threshold
+            ));
+        }
+
+        for (key, colour) in [
+            ("foreground", &self.foreground),
+            ("series_colour", &self.series_colour),
+            ("walk_colour", &self.walk_colour),
+            ("band_colour", &self.band_colour),
+        ]
+        .into_iter()
+        .chain(self.background.iter().map(|colour| ("background", colour)))
+        {
+            check_colour(key, colour)?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the background and foreground colours to an SVG drawn by plotlib, which always draws
+    /// black on a transparent background.
+    pub(crate) fn apply_to_svg(&self, svg: String) -> String {
+        let mut svg = svg
+            .replace(
+                "stroke=\"black\"",
+                &format!("stroke=\"{}\"", self.foreground),
+            )
+            .replace("<text ", &format!("<text fill=\"{}\" ", self.foreground));
+
+        if let Some(background) = &self.background {
+            if let Some(end) = svg.find('>') {
+                svg.insert_str(
+                    end + 1,
+                    &format!(
+                        "\n<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+                        background
+                    ),
+                );
+            }
+        }
+
+        svg
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::new(Theme::default())
+    }
+}
+
+/// Parts of a [`Style`] to replace, as read from a style file or given on the command line.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StyleOverrides {
+    pub theme: Option<Theme>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub background: Option<String>,
+    pub foreground: Option<String>,
+    pub series_colour: Option<String>,
+    pub walk_colour: Option<String>,
+    pub band_colour: Option<String>,
+    pub marker: Option<Marker>,
+    pub marker_size: Option<f32>,
+    pub walk_marker: Option<Marker>,
+    pub walk_size: Option<f32>,
+    pub show_walk: Option<bool>,
+    pub downsample: Option<Downsample>,
+    pub downsample_threshold: Option<usize>,
+    pub density: Option<bool>,
+}
+
+impl StyleOverrides {
+    /// Reads overrides from a TOML file, whose keys match the fields of [`Style`] plus `theme`.
+    /// Unknown keys are an error.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read style file {}", path.display()))?;
+
+        toml::from_str(&text).with_context(|| format!("Invalid style file {}", path.display()))
+    }
+
+    /// Takes everything set here, falling back to `other` for anything that isn't.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            theme: self.theme.or(other.theme),
+            width: self.width.or(other.width),
+            height: self.height.or(other.height),
+            background: self.background.or(other.background),
+            foreground: self.foreground.or(other.foreground),
+            series_colour: self.series_colour.or(other.series_colour),
+            walk_colour: self.walk_colour.or(other.walk_colour),
+            band_colour: self.band_colour.or(other.band_colour),
+            marker: self.marker.or(other.marker),
+            marker_size: self.marker_size.or(other.marker_size),
+            walk_marker: self.walk_marker.or(other.walk_marker),
+            walk_size: self.walk_size.or(other.walk_size),
+            show_walk: self.show_walk.or(other.show_walk),
+            downsample: self.downsample.or(other.downsample),
+            downsample_threshold: self.downsample_threshold.or(other.downsample_threshold),
+            density: self.density.or(other.density),
+        }
+    }
+}
+
+/// Accepts `#rgb` and `#rrggbb` hex colours, and plain names like `black`.
+fn check_colour(key: &str, colour: &str) -> Result<(), Error> {
+    let valid = match colour.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => !colour.is_empty() && colour.chars().all(|c| c.is_ascii_alphabetic()),
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Invalid colour '{}' for {}, expected something like #DD3355 or black",
+            colour,
+            key
+        ))
+    }
+}
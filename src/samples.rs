@@ -0,0 +1,28 @@
+use itertools::Itertools;
+use rand::Rng;
+
+/// Counts from 0 up to `range` over and over, until `point_count` values are produced.
+///
+/// This is about as badly distributed a sequence as you can get, which makes it a useful baseline.
+pub fn sequence(range: u64, point_count: u64) -> Vec<u64> {
+    stream_sequence(range, point_count).collect_vec()
+}
+
+/// The values of [`sequence`] one at a time, without keeping them.
+pub fn stream_sequence(range: u64, point_count: u64) -> impl Iterator<Item = u64> {
+    (0..range).cycle().take(point_count as usize)
+}
+
+/// Draws `point_count` values in `0..range` from `rng`.
+pub fn from_rng<R: Rng + ?Sized>(rng: &mut R, range: u64, point_count: u64) -> Vec<u64> {
+    stream_from_rng(rng, range, point_count).collect_vec()
+}
+
+/// Draws `point_count` values in `0..range` from `rng` one at a time, without keeping them.
+pub fn stream_from_rng<R: Rng>(
+    mut rng: R,
+    range: u64,
+    point_count: u64,
+) -> impl Iterator<Item = u64> {
+    (0..point_count).map(move |_| rng.gen_range(0..range))
+}
@@ -0,0 +1,363 @@
+//! Analysis of samples one at a time, for runs too long to keep every sample in memory.
+//!
+//! Only the histogram, the running moments, the fitness walk's statistics and a downsampled series
+//! for drawing are kept, so memory use doesn't grow with the number of samples.
+
+use std::time::Instant;
+
+use anyhow::{anyhow, Error};
+use log::info;
+
+use crate::analysis::chi_squared::chi_squared_of_histogram;
+use crate::analysis::{bin_of, empty_histogram, AnalysisOptions, Bin, TestResult};
+use crate::fitness::{Fitness, WalkTracker};
+
+/// Number of buckets a series is kept to at least, when there are enough samples.
+pub const DEFAULT_SERIES_BUCKETS: usize = 2000;
+
+/// Running mean and variance of a stream of points, by Welford's method, along with the smallest
+/// and largest seen.
+#[derive(Clone, Copy, Debug)]
+pub struct Moments {
+    count: u64,
+    mean: f64,
+    sum_of_squares: f64,
+    min: u64,
+    max: u64,
+}
+
+impl Default for Moments {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            mean: 0.,
+            sum_of_squares: 0.,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+}
+
+impl Moments {
+    /// Takes the next point.
+    pub fn push(&mut self, point: u64) {
+        self.count += 1;
+
+        let delta = point as f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.sum_of_squares += delta * (point as f64 - self.mean);
+
+        self.min = self.min.min(point);
+        self.max = self.max.max(point);
+    }
+
+    /// Number of points seen.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Population variance of the points seen, or 0 if there aren't any.
+    pub fn variance(&self) -> f64 {
+        if self.count == 0 {
+            0.
+        } else {
+            self.sum_of_squares / self.count as f64
+        }
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.max)
+    }
+}
+
+/// A run of consecutive samples, reduced to the smallest and largest of them and how low and high
+/// the fitness walk went over them.
+#[derive(Clone, Copy, Debug)]
+pub struct Bucket {
+    /// Index of the first sample in the bucket.
+    pub start: u64,
+
+    /// Number of samples in the bucket.
+    pub len: u64,
+
+    pub min: u64,
+    pub max: u64,
+    pub walk_min: i64,
+    pub walk_max: i64,
+}
+
+impl Bucket {
+    fn new(start: u64, point: u64, walk: i64) -> Self {
+        Self {
+            start,
+            len: 1,
+            min: point,
+            max: point,
+            walk_min: walk,
+            walk_max: walk,
+        }
+    }
+
+    fn push(&mut self, point: u64, walk: i64) {
+        self.len += 1;
+        self.min = self.min.min(point);
+        self.max = self.max.max(point);
+        self.walk_min = self.walk_min.min(walk);
+        self.walk_max = self.walk_max.max(walk);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.len += other.len;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.walk_min = self.walk_min.min(other.walk_min);
+        self.walk_max = self.walk_max.max(other.walk_max);
+    }
+}
+
+/// Samples and their fitness walk, downsampled into buckets of equal length.
+///
+/// Every sample gets its own bucket to start with. Whenever there would be more than twice
+/// `buckets` of them, neighbouring buckets are merged in pairs, so once there are enough samples
+/// there are always between `buckets` and twice as many however long the series gets.
+#[derive(Clone, Debug)]
+pub struct Series {
+    target: usize,
+    width: u64,
+    len: u64,
+    buckets: Vec<Bucket>,
+}
+
+impl Series {
+    /// Starts an empty series, to be kept to at least `buckets` buckets.
+    pub fn new(buckets: usize) -> Self {
+        Self {
+            target: buckets.max(1),
+            width: 1,
+            len: 0,
+            buckets: Vec::new(),
+        }
+    }
+
+    /// Adds the next sample, and where the fitness walk was after it.
+    pub fn push(&mut self, point: u64, walk: i64) {
+        match self.buckets.last_mut() {
+            Some(last) if last.len < self.width => last.push(point, walk),
+            _ => {
+                if self.buckets.len() >= 2 * self.target {
+                    self.compact();
+                }
+
+                self.buckets.push(Bucket::new(self.len, point, walk));
+            }
+        }
+
+        self.len += 1;
+    }
+
+    /// Halves the number of buckets by merging each pair of them.
+    fn compact(&mut self) {
+        self.buckets = self
+            .buckets
+            .chunks(2)
+            .map(|pair| {
+                let mut bucket = pair[0];
+
+                if let Some(second) = pair.get(1) {
+                    bucket.merge(second);
+                }
+
+                bucket
+            })
+            .collect();
+
+        self.width *= 2;
+    }
+
+    /// Number of samples in the series, rather than the number of buckets.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn buckets(&self) -> &[Bucket] {
+        &self.buckets
+    }
+
+    /// Moves every walk position by `offset`.
+    fn offset_walk(&mut self, offset: i64) {
+        for bucket in &mut self.buckets {
+            bucket.walk_min += offset;
+            bucket.walk_max += offset;
+        }
+    }
+}
+
+/// Everything kept from a stream of samples once it's finished.
+#[derive(Clone, Debug)]
+pub struct StreamSummary {
+    pub moments: Moments,
+    pub histogram: Vec<Bin>,
+    pub chi_squared: TestResult,
+
+    /// The fitness walk's statistics. The walk itself is only kept downsampled, in `series`.
+    pub fitness: Fitness,
+
+    /// The samples and the fitness walk, which starts halfway up to the largest sample as
+    /// [`fitness_walk`] does.
+    ///
+    /// [`fitness_walk`]: crate::fitness::fitness_walk
+    pub series: Series,
+}
+
+/// Analyses samples in `0..range` as they arrive, keeping as little of them as it can.
+#[derive(Clone, Debug)]
+pub struct StreamingAnalysis {
+    range: u64,
+    histogram: Vec<Bin>,
+    moments: Moments,
+    walk: WalkTracker,
+    series: Series,
+}
+
+impl StreamingAnalysis {
+    /// Starts an analysis with a histogram of `bins` bins, as for [`histogram`], and a series kept
+    /// to at least `series_buckets` buckets.
+    ///
+    /// [`histogram`]: crate::analysis::histogram
+    pub fn new(range: u64, bins: usize, series_buckets: usize) -> Self {
+        Self {
+            range,
+            histogram: empty_histogram(range, bins),
+            moments: Moments::default(),
+            walk: WalkTracker::default(),
+            series: Series::new(series_buckets),
+        }
+    }
+
+    /// Takes the next sample, which must lie in `0..range`.
+    pub fn push(&mut self, point: u64) -> Result<(), Error> {
+        if point >= self.range {
+            return Err(anyhow!(
+                "Point {} is outside the range 0..{}",
+                point,
+                self.range
+            ));
+        }
+
+        let bin = bin_of(point, self.range, self.histogram.len());
+        self.histogram[bin].count += 1;
+
+        self.moments.push(point);
+        let displacement = self.walk.push(point);
+        self.series.push(point, displacement);
+
+        Ok(())
+    }
+
+    /// Runs the tests that can be worked out from what's been kept.
+    pub fn finish(mut self) -> Result<StreamSummary, Error> {
+        let Some(max) = self.moments.max() else {
+            return Err(anyhow!("Statistical tests need at least one point"));
+        };
+
+        self.series.offset_walk(max as i64 / 2);
+
+        Ok(StreamSummary {
+            chi_squared: chi_squared_of_histogram(&self.histogram, self.range)?,
+            fitness: self.walk.fitness(self.range, Vec::new()),
+            moments: self.moments,
+            histogram: self.histogram,
+            series: self.series,
+        })
+    }
+}
+
+/// Analyses every point from `points`, which should be uniformly distributed over `0..range`, with
+/// the histogram binned as in `options` and a series of at least `series_buckets` buckets.
+pub fn analyse(
+    points: impl IntoIterator<Item = u64>,
+    range: u64,
+    options: &AnalysisOptions,
+    series_buckets: usize,
+) -> Result<StreamSummary, Error> {
+    let start = Instant::now();
+    let mut analysis = StreamingAnalysis::new(range, options.bins, series_buckets);
+
+    for point in points {
+        analysis.push(point)?;
+    }
+
+    let summary = analysis.finish()?;
+
+    info!(
+        "Streamed {} points in {:.1?}",
+        summary.moments.count(),
+        start.elapsed()
+    );
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_merges_neighbouring_pairs() {
+        let mut series = Series::new(4);
+
+        for (i, point) in [5, 1, 7, 3, 2].into_iter().enumerate() {
+            series.push(point, i as i64 - 2);
+        }
+
+        series.compact();
+
+        let buckets = series
+            .buckets()
+            .iter()
+            .map(|b| (b.start, b.len, b.min, b.max, b.walk_min, b.walk_max))
+            .collect::<Vec<_>>();
+
+        // The odd one out at the end is kept on its own
+        assert_eq!(
+            buckets,
+            [(0, 2, 1, 5, -2, -1), (2, 2, 3, 7, 0, 1), (4, 1, 2, 2, 2, 2)]
+        );
+    }
+
+    #[test]
+    fn series_stays_between_target_and_twice_it() {
+        let mut series = Series::new(4);
+
+        for point in 0..1000 {
+            series.push(point, 0);
+
+            let buckets = series.buckets();
+            assert!(buckets.len() <= 8);
+
+            if series.len() >= 4 {
+                assert!(buckets.len() >= 4);
+            }
+
+            // Every bucket but the last is full, and they follow on from each other
+            let width = buckets[0].len;
+            assert!(buckets[..buckets.len() - 1].iter().all(|b| b.len == width));
+            assert!(buckets
+                .windows(2)
+                .all(|pair| pair[0].start + pair[0].len == pair[1].start));
+            assert_eq!(buckets.iter().map(|b| b.len).sum::<u64>(), series.len());
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! Sweeps over ranges and point counts, and summaries of how the tests fare across them.
+
+use std::fmt::{Display, Formatter, Write as _};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use log::info;
+use serde::{Deserialize, Deserializer};
+
+use crate::analysis::TestResult;
+
+/// Factor between values of a geometric progression when none is given.
+pub const DEFAULT_FACTOR: f64 = 10.;
+
+/// A single value, or a geometric progression of them, in a list of ranges or point counts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sweep {
+    Value(u64),
+
+    /// Starts at `start` and multiplies by `factor` until passing `end`, rounding each value to the
+    /// nearest whole number.
+    Geometric {
+        start: u64,
+        end: u64,
+        factor: f64,
+    },
+}
+
+impl Sweep {
+    /// Every value in the sweep, in increasing order.
+    pub fn values(&self) -> Vec<u64> {
+        match *self {
+            Self::Value(value) => vec![value],
+            Self::Geometric { start, end, factor } => {
+                let mut values = Vec::new();
+                let mut current = start as f64;
+
+                while current.round() <= end as f64 {
+                    let value = current.round() as u64;
+
+                    if values.last() != Some(&value) {
+                        values.push(value);
+                    }
+
+                    current *= factor;
+                }
+
+                values
+            }
+        }
+    }
+
+    /// Checks every value is at least `min`, and that a progression actually goes somewhere.
+    pub fn validate(&self, min: u64) -> Result<(), Error> {
+        match *self {
+            Self::Value(value) if value < min => {
+                Err(anyhow!("must be at least {}, not {}", min, value))
+            }
+            Self::Value(_) => Ok(()),
+            Self::Geometric { start, end, factor } => {
+                if start < min {
+                    Err(anyhow!(
+                        "progression must start at {} or more, not {}",
+                        min,
+                        start
+                    ))
+                } else if end < start {
+                    Err(anyhow!(
+                        "progression ends at {}, before its start {}",
+                        end,
+                        start
+                    ))
+                } else if factor.is_nan() || factor <= 1. {
+                    Err(anyhow!(
+                        "progression factor must be more than 1, not {}",
+                        factor
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/// Parses a single value like `1000`, or a progression like `1000..1000000*10` from 1000 to
+/// 1000000 multiplying by 10 each time. The `*<factor>` can be left off for a factor of 10.
+impl FromStr for Sweep {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            anyhow!(
+                "Invalid value '{}', expected a number or a progression like 1000..1000000*10",
+                s
+            )
+        };
+
+        let Some((start, rest)) = s.split_once("..") else {
+            return s.trim().parse().map(Self::Value).map_err(|_| invalid());
+        };
+
+        let (end, factor) = match rest.split_once('*') {
+            Some((end, factor)) => (end, factor.trim().parse().map_err(|_| invalid())?),
+            None => (rest, DEFAULT_FACTOR),
+        };
+
+        Ok(Self::Geometric {
+            start: start.trim().parse().map_err(|_| invalid())?,
+            end: end.trim().parse().map_err(|_| invalid())?,
+            factor,
+        })
+    }
+}
+
+impl Display for Sweep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Value(value) => write!(f, "{}", value),
+            Self::Geometric { start, end, factor } => write!(f, "{}..{}*{}", start, end, factor),
+        }
+    }
+}
+
+/// In a config file a sweep can be a number, a string as on the command line, or a table with a
+/// `start`, an `end` and an optional `factor`.
+impl<'de> Deserialize<'de> for Sweep {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Table {
+            start: u64,
+            end: u64,
+            factor: Option<f64>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Value(u64),
+            Spec(String),
+            Table(Table),
+        }
+
+        match Repr::deserialize(deserializer).map_err(|_| {
+            serde::de::Error::custom(
+                "expected a number, a progression like \"1000..1000000*10\", or a table with a start, end and factor",
+            )
+        })? {
+            Repr::Value(value) => Ok(Self::Value(value)),
+            Repr::Spec(spec) => spec.parse().map_err(serde::de::Error::custom),
+            Repr::Table(Table { start, end, factor }) => Ok(Self::Geometric {
+                start,
+                end,
+                factor: factor.unwrap_or(DEFAULT_FACTOR),
+            }),
+        }
+    }
+}
+
+/// Every value from each of `sweeps` in order, skipping any already seen.
+pub fn expand(sweeps: &[Sweep]) -> Vec<u64> {
+    let mut values = Vec::new();
+
+    for value in sweeps.iter().flat_map(Sweep::values) {
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    values
+}
+
+/// The outcome of one generator over one range and point count.
+#[derive(Clone, Debug)]
+pub struct SummaryRow {
+    /// Generator name, with its seed if it has one.
+    pub generator: String,
+    pub range: u64,
+    pub point_count: u64,
+    pub results: Vec<TestResult>,
+    pub score: f64,
+}
+
+/// Writes `rows` as CSV to `path`, with the statistic and p-value of each test, whether it passed
+/// at `significance`, and the fitness score. Every row should have the same tests in the same order.
+pub fn write_summary_csv(path: &Path, rows: &[SummaryRow], significance: f64) -> Result<(), Error> {
+    let mut csv = String::from("generator,range,points");
+
+    if let Some(row) = rows.first() {
+        for result in &row.results {
+            write!(
+                csv,
+                ",{0}_statistic,{0}_p_value,{0}_passed",
+                result.name.replace(' ', "_")
+            )?;
+        }
+    }
+
+    csv.push_str(",score\n");
+
+    for row in rows {
+        write!(csv, "{},{},{}", row.generator, row.range, row.point_count)?;
+
+        for result in &row.results {
+            write!(
+                csv,
+                ",{},{},{}",
+                result.statistic,
+                result.p_value,
+                result.passed(significance)
+            )?;
+        }
+
+        writeln!(csv, ",{}", row.score)?;
+    }
+
+    std::fs::write(path, csv)?;
+    info!("Wrote {}", path.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_values_and_progressions() {
+        assert_eq!("1000".parse::<Sweep>().unwrap(), Sweep::Value(1000));
+        assert_eq!(
+            "10..1000*2.5".parse::<Sweep>().unwrap(),
+            Sweep::Geometric {
+                start: 10,
+                end: 1000,
+                factor: 2.5
+            }
+        );
+        assert_eq!(
+            " 100 .. 10000 ".parse::<Sweep>().unwrap(),
+            Sweep::Geometric {
+                start: 100,
+                end: 10000,
+                factor: DEFAULT_FACTOR
+            }
+        );
+
+        for invalid in ["", "ten", "10..", "..10", "10..100*x", "-5"] {
+            assert!(invalid.parse::<Sweep>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn progressions_round_and_stop_at_the_end() {
+        assert_eq!(
+            "100..100000".parse::<Sweep>().unwrap().values(),
+            [100, 1000, 10000, 100000]
+        );
+        assert_eq!(
+            "10..1000*2.5".parse::<Sweep>().unwrap().values(),
+            [10, 25, 63, 156, 391, 977]
+        );
+
+        // Factors close to 1 round to the same value at first, which only appears once
+        assert_eq!("2..4*1.2".parse::<Sweep>().unwrap().values(), [2, 3, 4]);
+        assert_eq!(Sweep::Value(7).values(), [7]);
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for spec in ["1000", "10..1000*2.5"] {
+            let sweep = spec.parse::<Sweep>().unwrap();
+            assert_eq!(sweep.to_string().parse::<Sweep>().unwrap(), sweep);
+        }
+    }
+
+    #[test]
+    fn validate_checks_minimum_and_direction() {
+        let validate = |spec: &str| spec.parse::<Sweep>().unwrap().validate(3);
+
+        assert!(validate("3").is_ok());
+        assert!(validate("3..30*1.5").is_ok());
+        assert!(validate("2").is_err());
+        assert!(validate("2..30").is_err());
+        assert!(validate("30..3").is_err());
+        assert!(validate("3..30*1").is_err());
+    }
+
+    #[test]
+    fn expand_skips_repeats() {
+        let sweeps = ["10..1000", "100", "5"].map(|spec| spec.parse::<Sweep>().unwrap());
+        assert_eq!(expand(&sweeps), [10, 100, 1000, 5]);
+    }
+}
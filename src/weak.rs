@@ -0,0 +1,222 @@
+//! Classic generators with well known weaknesses, for calibrating what a bad generator looks like.
+//!
+//! Most of these only produce 15 or 31 bits per step. Each output comes from a single step, scaled up
+//! into the most significant bits like `rand() / RAND_MAX` would be, so consecutive outputs come from
+//! consecutive steps and any relationship between them is kept. The bits below that are always zero.
+
+use rand::{Error, RngCore, SeedableRng};
+use rand_core::impls;
+
+/// A generator that produces a fixed number of bits per step.
+pub(crate) trait Step {
+    /// Number of bits in each value returned from [`Step::step`].
+    const BITS: u32;
+
+    fn step(&mut self) -> u64;
+
+    /// Scales the output of the next step up into the most significant bits of a `u64`.
+    fn next_scaled(&mut self) -> u64 {
+        self.step() << (u64::BITS - Self::BITS)
+    }
+}
+
+macro_rules! impl_weak_rng {
+    ($rng:ident) => {
+        impl RngCore for $rng {
+            fn next_u32(&mut self) -> u32 {
+                (self.next_scaled() >> 32) as u32
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                self.next_scaled()
+            }
+
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                impls::fill_bytes_via_next(self, dest)
+            }
+
+            fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+                self.fill_bytes(dest);
+                Ok(())
+            }
+        }
+
+        impl SeedableRng for $rng {
+            type Seed = [u8; 8];
+
+            fn from_seed(seed: Self::Seed) -> Self {
+                Self::new(u64::from_le_bytes(seed))
+            }
+
+            /// Starts from `state` as given, rather than mixing it up first, so published sequences
+            /// can be reproduced.
+            fn seed_from_u64(state: u64) -> Self {
+                Self::new(state)
+            }
+        }
+    };
+}
+
+/// IBM's RANDU, `x = 65539 * x mod 2^31`. Every triple of outputs falls on one of 15 planes.
+pub struct Randu(u32);
+
+impl Randu {
+    pub fn new(seed: u64) -> Self {
+        // RANDU needs an odd seed
+        Self((seed as u32 & 0x7FFF_FFFF) | 1)
+    }
+}
+
+impl Step for Randu {
+    const BITS: u32 = 31;
+
+    fn step(&mut self) -> u64 {
+        self.0 = ((self.0 as u64 * 65539) & 0x7FFF_FFFF) as u32;
+        self.0 as u64
+    }
+}
+
+impl_weak_rng!(Randu);
+
+/// The LCG used by glibc's `rand()` when it has no extra state, `x = 1103515245 * x + 12345 mod
+/// 2^31`.
+pub struct GlibcRand(u32);
+
+impl GlibcRand {
+    pub fn new(seed: u64) -> Self {
+        Self(seed as u32 & 0x7FFF_FFFF)
+    }
+}
+
+impl Step for GlibcRand {
+    const BITS: u32 = 31;
+
+    fn step(&mut self) -> u64 {
+        self.0 = (self.0.wrapping_mul(1103515245).wrapping_add(12345)) & 0x7FFF_FFFF;
+        self.0 as u64
+    }
+}
+
+impl_weak_rng!(GlibcRand);
+
+/// The LCG used by MSVC's `rand()`, `x = 214013 * x + 2531011 mod 2^32`, returning bits 16 to
+/// 30 of the state.
+pub struct MsvcRand(u32);
+
+impl MsvcRand {
+    pub fn new(seed: u64) -> Self {
+        Self(seed as u32)
+    }
+}
+
+impl Step for MsvcRand {
+    const BITS: u32 = 15;
+
+    fn step(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(214013).wrapping_add(2531011);
+        ((self.0 >> 16) & 0x7FFF) as u64
+    }
+}
+
+impl_weak_rng!(MsvcRand);
+
+/// Von Neumann's middle-square method on a 32 bit state, which quickly falls into short cycles or
+/// gets stuck at zero.
+pub struct MiddleSquare(u32);
+
+impl MiddleSquare {
+    pub fn new(seed: u64) -> Self {
+        Self(seed as u32)
+    }
+}
+
+impl Step for MiddleSquare {
+    const BITS: u32 = 32;
+
+    fn step(&mut self) -> u64 {
+        let square = self.0 as u64 * self.0 as u64;
+        self.0 = (square >> 16) as u32;
+        self.0 as u64
+    }
+}
+
+impl_weak_rng!(MiddleSquare);
+
+/// Park and Miller's MINSTD Lehmer generator, `x = 16807 * x mod (2^31 - 1)`.
+pub struct Minstd(u32);
+
+impl Minstd {
+    const MODULUS: u64 = 0x7FFF_FFFF;
+
+    pub fn new(seed: u64) -> Self {
+        // Zero is a fixed point, so is never a valid state
+        Self(((seed % Self::MODULUS) as u32).max(1))
+    }
+}
+
+impl Step for Minstd {
+    const BITS: u32 = 31;
+
+    fn step(&mut self) -> u64 {
+        self.0 = ((self.0 as u64 * 16807) % Self::MODULUS) as u32;
+        self.0 as u64
+    }
+}
+
+impl_weak_rng!(Minstd);
+
+/// A full 64 bit LCG using Knuth's MMIX constants, truncated to the low 32 bits of its state
+/// rather than the high ones. Bit `k` of the output has a period of only `2^(k + 1)`.
+pub struct TruncatedLcg(u64);
+
+impl TruncatedLcg {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+impl Step for TruncatedLcg {
+    const BITS: u32 = 32;
+
+    fn step(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 & 0xFFFF_FFFF
+    }
+}
+
+impl_weak_rng!(TruncatedLcg);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The state after each step, from the top 31 bits of each output.
+    fn states<R: RngCore>(mut rng: R, count: usize) -> Vec<u64> {
+        (0..count).map(|_| rng.next_u64() >> 33).collect()
+    }
+
+    #[test]
+    fn randu_starts_from_the_given_seed() {
+        assert_eq!(states(Randu::seed_from_u64(1), 3), [65539, 393225, 1769499]);
+    }
+
+    #[test]
+    fn minstd_matches_park_and_miller() {
+        // Park and Miller's check that the 10000th value from a seed of 1 is 1043618065
+        let mut rng = Minstd::seed_from_u64(1);
+        let last = (0..10000).map(|_| rng.next_u64() >> 33).last();
+
+        assert_eq!(last, Some(1043618065));
+    }
+
+    #[test]
+    fn glibc_rand_starts_from_the_given_seed() {
+        assert_eq!(
+            states(GlibcRand::seed_from_u64(1), 2),
+            [1103527590, 377401575]
+        );
+    }
+}